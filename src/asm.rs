@@ -0,0 +1,916 @@
+use crate::cpu::PROGRAM_START;
+use crate::instruction::Instruction;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+// An assembler for the CHIPPER syntax of the sources in games/SOURCES
+//
+//   option binary
+//   Paddle  = #3F
+//   Loop:   LD   V0, Paddle & #0F    ; comment
+//           DRW  V0, V1, 6
+//           JP   Loop
+//   Sprite: DB   $1.......
+//
+// Numbers are decimal, #hex, $binary (where . is a zero as well) or @octal.
+// Expressions use ( ) ~ * / + - \ % < > & ^ | from the tightest to the
+// loosest binding, where \ divides and < and > shift. `.` and `?` are the
+// address of the current line. Symbols and mnemonics are not case sensitive.
+//
+// Besides the CHIPPER directives (DB, DW, DA, DS, ORG, ALIGN, EQU / =,
+// DEFINE, UNDEF, IFDEF, IFUND, ELSE, ENDIF, END, OPTION, USED, XREF) the
+// older mnemonics that some of the sources use are understood as well:
+// MOV, MVI, JMP, JSR, RTS, SKEQ, SKNE, SKPR, SKUP, KEY, SDELAY, GDELAY,
+// SSOUND, RANDOM, SPRITE, ADI, LDR, STR, BCD, FONT, XFONT, RSB and HALT,
+// with R0-RF as another name for the V registers.
+
+// An error in the source, with the line it was found on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// The bytes that one line of the source assembled into
+pub struct ListingLine {
+    pub line: usize,
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub source: String,
+}
+
+pub struct Assembly {
+    // The program, loaded at the program start
+    pub binary: Vec<u8>,
+    // All labels and constants with their values
+    pub symbols: BTreeMap<String, i64>,
+    pub listing: Vec<ListingLine>,
+}
+
+impl Assembly {
+    // The source next to the address and bytes of each line
+    pub fn listing(&self) -> String {
+        let mut text = String::new();
+        for line in &self.listing {
+            // Long data lines continue on the following lines
+            let chunks: Vec<&[u8]> = line.bytes.chunks(4).collect();
+            let hex =
+                |chunk: &[u8]| -> String { chunk.iter().map(|b| format!("{:02X}", b)).collect() };
+            let first = chunks.first().map_or(String::new(), |chunk| hex(chunk));
+            if line.bytes.is_empty() {
+                text.push_str(&format!("{:5}            {}\n", line.line, line.source));
+            } else {
+                text.push_str(&format!(
+                    "{:5} {:04X} {:<8} {}\n",
+                    line.line, line.address, first, line.source
+                ));
+            }
+            for (idx, chunk) in chunks.iter().enumerate().skip(1) {
+                text.push_str(&format!(
+                    "      {:04X} {}\n",
+                    line.address as usize + idx * 4,
+                    hex(chunk)
+                ));
+            }
+        }
+        text
+    }
+
+    // One symbol per line, sorted by name
+    pub fn symbol_table(&self) -> String {
+        self.symbols
+            .iter()
+            .map(|(name, value)| format!("{:<20} #{:04X}\n", name, value))
+            .collect()
+    }
+}
+
+// Assemble a source, the names in defines count as DEFINEd for IFDEF
+pub fn assemble(source: &str, defines: &[&str]) -> Result<Assembly, AsmError> {
+    let mut assembler = Assembler::new(defines);
+    // The first pass only finds the address of every label, the second
+    // one has all of them and produces the bytes
+    assembler.pass(source, false)?;
+    assembler.pass(source, true)?;
+
+    Ok(Assembly {
+        binary: assembler.binary,
+        symbols: assembler.symbols,
+        listing: assembler.listing,
+    })
+}
+
+struct Assembler {
+    symbols: BTreeMap<String, i64>,
+    // Symbols defined in the first pass, a second definition in the same
+    // pass is an error
+    defined: HashSet<String>,
+    initial_defines: Vec<String>,
+    defines: HashSet<String>,
+    address: u16,
+    align: bool,
+    // Whether the second pass is running, the first one tolerates
+    // symbols that are defined further down
+    final_pass: bool,
+    // Whether the current line uses the grouping of the older assembler
+    legacy: bool,
+    binary: Vec<u8>,
+    listing: Vec<ListingLine>,
+}
+
+impl Assembler {
+    fn new(defines: &[&str]) -> Assembler {
+        Assembler {
+            symbols: BTreeMap::new(),
+            defined: HashSet::new(),
+            initial_defines: defines.iter().map(|name| name.to_uppercase()).collect(),
+            defines: HashSet::new(),
+            address: PROGRAM_START,
+            align: true,
+            final_pass: false,
+            legacy: false,
+            binary: Vec::new(),
+            listing: Vec::new(),
+        }
+    }
+
+    fn pass(&mut self, source: &str, final_pass: bool) -> Result<(), AsmError> {
+        self.final_pass = final_pass;
+        self.defines = self.initial_defines.iter().cloned().collect();
+        self.defined.clear();
+        self.address = PROGRAM_START;
+        self.align = true;
+        self.binary.clear();
+        self.listing.clear();
+
+        // Every IFDEF pushes whether its lines are assembled
+        let mut conditions: Vec<bool> = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        for (idx, text) in source.lines().enumerate() {
+            let number = idx + 1;
+            let error = |message: String| AsmError {
+                line: number,
+                message,
+            };
+            let line = parse_line(text).map_err(error)?;
+            let active = conditions.iter().all(|active| *active);
+
+            // A label names the next instruction or data, which may still
+            // move to align it
+            let constant = matches!(line.mnemonic.as_deref(), Some("EQU") | Some("="));
+            if active && !constant {
+                pending.extend(line.label.iter().cloned());
+            }
+
+            // Conditionals are followed even in lines that are skipped
+            match line.mnemonic.as_deref() {
+                Some("IFDEF") | Some("IFUND") => {
+                    let name = single(&line.operands).map_err(error)?.to_uppercase();
+                    let defined = self.defines.contains(&name);
+                    conditions.push(defined == (line.mnemonic.as_deref() == Some("IFDEF")));
+                    continue;
+                }
+                Some("ELSE") => {
+                    let last = conditions
+                        .last_mut()
+                        .ok_or_else(|| error("ELSE without IFDEF".to_string()))?;
+                    *last = !*last;
+                    continue;
+                }
+                Some("ENDIF") => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error("ENDIF without IFDEF".to_string()))?;
+                    continue;
+                }
+                _ => {}
+            }
+            if !active {
+                continue;
+            }
+            if line.mnemonic.as_deref() == Some("END") {
+                break;
+            }
+
+            // Instructions are word aligned, unless turned off
+            let instruction = line.mnemonic.as_deref().is_some_and(is_instruction);
+            if self.align && self.address % 2 == 1 && instruction {
+                if final_pass {
+                    self.emit(&[0]).map_err(error)?;
+                }
+                self.address += 1;
+            }
+
+            if line.mnemonic.is_some() && !constant {
+                for label in pending.drain(..) {
+                    self.define(&label, self.address as i64).map_err(error)?;
+                }
+            }
+
+            let start = self.address;
+            let bytes = self.statement(&line).map_err(error)?;
+            if final_pass {
+                self.emit(&bytes).map_err(error)?;
+                self.listing.push(ListingLine {
+                    line: number,
+                    address: start,
+                    bytes: bytes.clone(),
+                    source: text.trim_end().to_string(),
+                });
+            }
+            self.address = self.address.wrapping_add(bytes.len() as u16);
+        }
+
+        let lines = source.lines().count();
+        for label in pending {
+            self.define(&label, self.address as i64)
+                .map_err(|message| AsmError {
+                    line: lines,
+                    message,
+                })?;
+        }
+        if !conditions.is_empty() {
+            return Err(AsmError {
+                line: lines,
+                message: "IFDEF without ENDIF".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    // Put bytes in the binary at the current address
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), String> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        if self.address < PROGRAM_START {
+            return Err(format!(
+                "address #{:03X} is below the program start",
+                self.address
+            ));
+        }
+        let offset = (self.address - PROGRAM_START) as usize;
+        if self.binary.len() < offset + bytes.len() {
+            self.binary.resize(offset + bytes.len(), 0);
+        }
+        self.binary[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), String> {
+        let name = name.to_uppercase();
+        if !self.defined.insert(name.clone()) {
+            return Err(format!("symbol '{}' is defined twice", name));
+        }
+        self.symbols.insert(name, value);
+        Ok(())
+    }
+
+    // Handle a line and return the bytes it assembles into
+    fn statement(&mut self, line: &Line) -> Result<Vec<u8>, String> {
+        let mnemonic = match line.mnemonic {
+            Some(ref mnemonic) => mnemonic.as_str(),
+            None => return Ok(Vec::new()),
+        };
+        let operands = &line.operands;
+        self.legacy = LEGACY_INSTRUCTIONS.contains(&mnemonic);
+
+        // Constants take the name of the label instead of its address
+        if mnemonic == "EQU" || mnemonic == "=" {
+            let name = line.label.as_ref().ok_or("a constant needs a name")?;
+            let value = self.eval(single(operands)?)?;
+            return self.define(name, value).map(|_| Vec::new());
+        }
+
+        match mnemonic {
+            "OPTION" | "USED" | "XREF" => Ok(Vec::new()),
+            "INCLUDE" => Err("INCLUDE is not supported".to_string()),
+            "ALIGN" => {
+                self.align = match single(operands)?.to_uppercase().as_str() {
+                    "ON" => true,
+                    "OFF" => false,
+                    other => return Err(format!("ALIGN needs ON or OFF, not '{}'", other)),
+                };
+                Ok(Vec::new())
+            }
+            "DEFINE" => {
+                self.defines.insert(single(operands)?.to_uppercase());
+                Ok(Vec::new())
+            }
+            "UNDEF" => {
+                self.defines.remove(&single(operands)?.to_uppercase());
+                Ok(Vec::new())
+            }
+            "ORG" => {
+                self.address = self.address_operand(single(operands)?, 0xFFFF)?;
+                Ok(Vec::new())
+            }
+            "DS" => {
+                let size = self.eval(single(operands)?)?;
+                if !(0..=0xFFFF).contains(&size) {
+                    return Err(format!("invalid size {}", size));
+                }
+                Ok(vec![0; size as usize])
+            }
+            "DB" => operands.iter().map(|operand| self.byte(operand)).collect(),
+            "DW" => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    let word = self.eval(operand)?;
+                    if !(-0x8000..=0xFFFF).contains(&word) {
+                        return Err(format!("{} does not fit in a word", word));
+                    }
+                    bytes.push((word >> 8) as u8);
+                    bytes.push(word as u8);
+                }
+                Ok(bytes)
+            }
+            "DA" => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    bytes.extend(string(operand)?);
+                }
+                Ok(bytes)
+            }
+            _ => {
+                let opcode = self.instruction(mnemonic, operands)?;
+                let mut bytes = vec![(opcode[0] >> 8) as u8, opcode[0] as u8];
+                if let Some(word) = opcode.get(1) {
+                    bytes.push((word >> 8) as u8);
+                    bytes.push(*word as u8);
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    // The words of an instruction, F000 NNNN is the only one with two
+    fn instruction(&self, mnemonic: &str, operands: &[String]) -> Result<Vec<u16>, String> {
+        let ops: Vec<Operand> = operands.iter().map(|op| Operand::parse(op)).collect();
+        let wrong = || {
+            Err(format!(
+                "invalid operands for {}: '{}'",
+                mnemonic,
+                operands.join(", ")
+            ))
+        };
+
+        // The older mnemonics are other names for CHIPPER instructions
+        let mnemonic = match mnemonic {
+            "MOV" => "LD",
+            "JMP" => "JP",
+            "JSR" => "CALL",
+            "RTS" => "RET",
+            "SKEQ" => "SE",
+            "SKNE" => "SNE",
+            "SKPR" => "SKP",
+            "SKUP" => "SKNP",
+            "RANDOM" => "RND",
+            "SPRITE" => "DRW",
+            "RSB" => "SUBN",
+            "HALT" => "EXIT",
+            other => other,
+        };
+
+        use Instruction::*;
+        use Operand::*;
+        let instruction = match (mnemonic, ops.as_slice()) {
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("SCR", []) => Scr,
+            ("SCL", []) => Scl,
+            ("EXIT", []) => Exit,
+            ("LOW", []) => Low,
+            ("HIGH", []) => High,
+            ("AUDIO", []) => Audio,
+            ("SCD", [Expr(n)]) => Scd(self.nibble(n)?),
+            ("SCU", [Expr(n)]) => Scu(self.nibble(n)?),
+            // Neither is a single word of the instruction set
+            ("SYS", [Expr(a)]) => return Ok(vec![self.address_operand(a, 0xFFF)?]),
+            ("LD", [I, Long(a)]) => {
+                return Ok(vec![LdLong.encode(), self.address_operand(a, 0xFFFF)?])
+            }
+            ("JP", [Expr(a)]) => Jp(self.address_operand(a, 0xFFF)?),
+            ("JP", [V(0), Expr(a)]) => JpV0(self.address_operand(a, 0xFFF)?),
+            ("CALL", [Expr(a)]) => Call(self.address_operand(a, 0xFFF)?),
+            ("SE", [V(x), V(y)]) => SeReg { x: *x, y: *y },
+            ("SE", [V(x), Expr(k)]) => SeByte {
+                x: *x,
+                kk: self.byte(k)?,
+            },
+            ("SNE", [V(x), V(y)]) => SneReg { x: *x, y: *y },
+            ("SNE", [V(x), Expr(k)]) => SneByte {
+                x: *x,
+                kk: self.byte(k)?,
+            },
+            ("SAVE", [V(x), V(y)]) => Save { x: *x, y: *y },
+            ("LOAD", [V(x), V(y)]) => Load { x: *x, y: *y },
+            ("LD", [V(x), V(y)]) => LdReg { x: *x, y: *y },
+            ("LD", [V(x), DT]) => LdVxDt(*x),
+            ("LD", [V(x), K]) => LdVxK(*x),
+            ("LD", [V(x), IndirectI]) => LdVxI(*x),
+            ("LD", [V(x), R]) => LdVxR(*x),
+            ("LD", [V(x), Expr(k)]) => LdByte {
+                x: *x,
+                kk: self.byte(k)?,
+            },
+            ("LD", [I, Expr(a)]) => LdI(self.address_operand(a, 0xFFF)?),
+            ("LD", [DT, V(x)]) => LdDtVx(*x),
+            ("LD", [ST, V(x)]) => LdStVx(*x),
+            ("LD", [F, V(x)]) => LdF(*x),
+            ("LD", [HF, V(x)]) => LdHf(*x),
+            ("LD", [B, V(x)]) => LdB(*x),
+            ("LD", [IndirectI, V(x)]) => LdIVx(*x),
+            ("LD", [R, V(x)]) => LdRVx(*x),
+            ("ADD", [V(x), V(y)]) => AddReg { x: *x, y: *y },
+            ("ADD", [V(x), Expr(k)]) => AddByte {
+                x: *x,
+                kk: self.byte(k)?,
+            },
+            ("ADD", [I, V(x)]) => AddI(*x),
+            ("OR", [V(x), V(y)]) => Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Sub { x: *x, y: *y },
+            ("SHR", [V(x)]) => Shr { x: *x, y: 0 },
+            ("SHR", [V(x), V(y)]) => Shr { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Subn { x: *x, y: *y },
+            ("SHL", [V(x)]) => Shl { x: *x, y: 0 },
+            ("SHL", [V(x), V(y)]) => Shl { x: *x, y: *y },
+            ("RND", [V(x), Expr(k)]) => Rnd {
+                x: *x,
+                kk: self.byte(k)?,
+            },
+            ("DRW", [V(x), V(y), Expr(n)]) => Drw {
+                x: *x,
+                y: *y,
+                n: self.nibble(n)?,
+            },
+            ("SKP", [V(x)]) => Skp(*x),
+            ("SKNP", [V(x)]) => Sknp(*x),
+            ("PLANE", [Expr(n)]) => Plane(self.nibble(n)?),
+            ("PITCH", [V(x)]) => Pitch(*x),
+            ("MVI", [Expr(a)]) => LdI(self.address_operand(a, 0xFFF)?),
+            ("KEY", [V(x)]) => LdVxK(*x),
+            ("SDELAY", [V(x)]) => LdDtVx(*x),
+            ("GDELAY", [V(x)]) => LdVxDt(*x),
+            ("SSOUND", [V(x)]) => LdStVx(*x),
+            ("ADI", [V(x)]) => AddI(*x),
+            ("FONT", [V(x)]) => LdF(*x),
+            ("XFONT", [V(x)]) => LdHf(*x),
+            ("BCD", [V(x)]) => LdB(*x),
+            ("STR", [Range(0, x)]) => LdIVx(*x),
+            ("LDR", [Range(0, x)]) => LdVxI(*x),
+            _ if is_instruction(mnemonic) => return wrong(),
+            _ => return Err(format!("unknown mnemonic '{}'", mnemonic)),
+        };
+        Ok(vec![instruction.encode()])
+    }
+
+    fn nibble(&self, expr: &str) -> Result<u8, String> {
+        let value = self.eval(expr)?;
+        if !(0..=0xF).contains(&value) {
+            return Err(format!("{} does not fit in a nibble", value));
+        }
+        Ok(value as u8)
+    }
+
+    // Bytes may be given as signed numbers
+    fn byte(&self, expr: &str) -> Result<u8, String> {
+        let value = self.eval(expr)?;
+        if !(-0x80..=0xFF).contains(&value) {
+            return Err(format!("{} does not fit in a byte", value));
+        }
+        Ok(value as u8)
+    }
+
+    fn address_operand(&self, expr: &str, max: i64) -> Result<u16, String> {
+        let value = self.eval(expr)?;
+        if !(0..=max).contains(&value) {
+            return Err(format!("address {:#X} is out of range", value));
+        }
+        Ok(value as u16)
+    }
+
+    fn eval(&self, expr: &str) -> Result<i64, String> {
+        let mut parser = Expression {
+            chars: expr.chars().collect(),
+            pos: 0,
+            assembler: self,
+        };
+        let value = parser.or()?;
+        parser.skip_spaces();
+        if parser.pos < parser.chars.len() {
+            return Err(format!(
+                "unexpected '{}' in '{}'",
+                parser.chars[parser.pos], expr
+            ));
+        }
+        Ok(value)
+    }
+
+    fn lookup(&self, name: &str) -> Result<i64, String> {
+        match self.symbols.get(&name.to_uppercase()) {
+            Some(value) => Ok(*value),
+            // Labels further down are only known in the second pass
+            None if !self.final_pass => Ok(0),
+            None => Err(format!("unknown symbol '{}'", name)),
+        }
+    }
+}
+
+// Everything that is not an instruction
+const DIRECTIVES: [&str; 19] = [
+    "OPTION", "USED", "XREF", "ALIGN", "DEFINE", "UNDEF", "ORG", "DS", "DB", "DW", "DA", "EQU",
+    "=", "IFDEF", "IFUND", "ELSE", "ENDIF", "END", "INCLUDE",
+];
+
+const INSTRUCTIONS: [&str; 32] = [
+    "CLS", "RET", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SCD", "SCU", "SYS", "JP", "CALL", "SE",
+    "SNE", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SHR", "SUBN", "SHL", "RND", "DRW", "SKP",
+    "SKNP", "SAVE", "LOAD", "PLANE", "AUDIO", "PITCH",
+];
+
+// The mnemonics of the older assembler, it grouped + and - from the right
+// and the sources written for it rely on that
+const LEGACY_INSTRUCTIONS: [&str; 23] = [
+    "MOV", "MVI", "JMP", "JSR", "RTS", "SKEQ", "SKNE", "SKPR", "SKUP", "KEY", "SDELAY", "GDELAY",
+    "SSOUND", "RANDOM", "SPRITE", "ADI", "LDR", "STR", "BCD", "FONT", "XFONT", "RSB", "HALT",
+];
+
+// Whether a mnemonic assembles into an instruction rather than data
+fn is_instruction(mnemonic: &str) -> bool {
+    INSTRUCTIONS.contains(&mnemonic) || LEGACY_INSTRUCTIONS.contains(&mnemonic)
+}
+
+fn is_mnemonic(word: &str) -> bool {
+    let word = word.to_uppercase();
+    is_instruction(&word) || DIRECTIVES.contains(&word.as_str())
+}
+
+// A line split into its parts, the mnemonic in upper case
+struct Line {
+    label: Option<String>,
+    mnemonic: Option<String>,
+    operands: Vec<String>,
+}
+
+fn parse_line(text: &str) -> Result<Line, String> {
+    let code = strip_comment(text);
+    let mut rest = code.trim();
+    let mut label = None;
+
+    // A label ends with a colon, and can be followed by code right away
+    let first = rest.split_whitespace().next().unwrap_or("");
+    if let Some(colon) = first.find(':') {
+        label = Some(first[..colon].to_string());
+        rest = rest[colon + 1..].trim_start();
+    }
+
+    // Labels in the first column do not need the colon, and neither do
+    // the names of constants: NAME EQU value
+    let mut words = rest.splitn(2, char::is_whitespace);
+    let word = words.next().unwrap_or("");
+    let after = words.next().unwrap_or("").trim_start();
+    if label.is_none() && !word.is_empty() {
+        let next = after.split_whitespace().next().unwrap_or("");
+        let first_column = !code.starts_with(char::is_whitespace) && !is_mnemonic(word);
+        if first_column || next.eq_ignore_ascii_case("EQU") || next == "=" || after.starts_with('=')
+        {
+            label = Some(word.to_string());
+            rest = after;
+        }
+    }
+
+    // The = of a constant does not need spaces around it
+    let (mnemonic, operands) = if let Some(value) = rest.strip_prefix('=') {
+        ("=".to_string(), value)
+    } else {
+        let mut parts = rest.splitn(2, char::is_whitespace);
+        let mnemonic = parts.next().unwrap_or("").to_uppercase();
+        (mnemonic, parts.next().unwrap_or(""))
+    };
+    if label.as_deref() == Some("") {
+        return Err("empty label".to_string());
+    }
+
+    Ok(Line {
+        label,
+        mnemonic: if mnemonic.is_empty() {
+            None
+        } else {
+            Some(mnemonic)
+        },
+        operands: split_operands(operands),
+    })
+}
+
+// Everything before a ; that is not in a string
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = false;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            ';' if !quoted => return &text[..idx],
+            _ => {}
+        }
+    }
+    text
+}
+
+fn split_operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        if c == ',' && !quoted {
+            operands.push(current.trim().to_string());
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    // A trailing comma is tolerated
+    if !current.trim().is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+fn single(operands: &[String]) -> Result<&str, String> {
+    match operands {
+        [operand] => Ok(operand),
+        _ => Err(format!("expected one operand, found {}", operands.len())),
+    }
+}
+
+// The bytes of a quoted string, '' is a quote inside it
+fn string(operand: &str) -> Result<Vec<u8>, String> {
+    let inner = operand
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+        .ok_or_else(|| format!("expected a quoted string, found '{}'", operand))?;
+    Ok(inner.replace("''", "'").into_bytes())
+}
+
+// The kinds of operand an instruction can have
+enum Operand {
+    V(u8),
+    Range(u8, u8),
+    I,
+    IndirectI,
+    DT,
+    ST,
+    K,
+    F,
+    HF,
+    B,
+    R,
+    Long(String),
+    Expr(String),
+}
+
+impl Operand {
+    fn parse(text: &str) -> Operand {
+        let upper = text.to_uppercase();
+        let register = |name: &str| -> Option<u8> {
+            let name = name.trim();
+            let mut chars = name.chars();
+            match (chars.next(), chars.next(), chars.next()) {
+                (Some('V'), Some(digit), None) | (Some('R'), Some(digit), None) => {
+                    digit.to_digit(16).map(|x| x as u8)
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(x) = register(&upper) {
+            return Operand::V(x);
+        }
+        if let Some((from, to)) = upper.split_once('-') {
+            if let (Some(from), Some(to)) = (register(from), register(to)) {
+                return Operand::Range(from, to);
+            }
+        }
+        match upper.as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::DT,
+            "ST" => Operand::ST,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::HF,
+            "B" => Operand::B,
+            "R" => Operand::R,
+            _ if upper.starts_with("LONG ") => Operand::Long(text[5..].trim().to_string()),
+            _ => Operand::Expr(text.to_string()),
+        }
+    }
+}
+
+// A recursive descent parser for expressions, from the loosest binding
+// operator down to single values
+struct Expression<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    assembler: &'a Assembler,
+}
+
+impl Expression<'_> {
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    // Consume the operator if it comes next
+    fn accept(&mut self, op: char) -> bool {
+        self.skip_spaces();
+        if self.chars.get(self.pos) == Some(&op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        let mut value = self.xor()?;
+        while self.accept('|') {
+            value |= self.xor()?;
+        }
+        Ok(value)
+    }
+
+    fn xor(&mut self) -> Result<i64, String> {
+        let mut value = self.and()?;
+        while self.accept('^') {
+            value ^= self.and()?;
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        let mut value = self.shift()?;
+        while self.accept('&') {
+            value &= self.shift()?;
+        }
+        Ok(value)
+    }
+
+    fn shift(&mut self) -> Result<i64, String> {
+        let mut value = self.quotient()?;
+        loop {
+            if self.accept('<') {
+                value <<= self.quotient()?.clamp(0, 63);
+            } else if self.accept('>') {
+                value >>= self.quotient()?.clamp(0, 63);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // CHIPPER's \ and % bind looser than + and -, so that END - START \ 4
+    // counts the entries of a table
+    fn quotient(&mut self) -> Result<i64, String> {
+        let mut value = self.sum()?;
+        loop {
+            let modulo = if self.accept('\\') {
+                false
+            } else if self.accept('%') {
+                true
+            } else {
+                return Ok(value);
+            };
+            let divisor = self.sum()?;
+            if divisor == 0 {
+                return Err("division by zero".to_string());
+            }
+            value = if modulo {
+                value.wrapping_rem(divisor)
+            } else {
+                value.wrapping_div(divisor)
+            };
+        }
+    }
+
+    fn sum(&mut self) -> Result<i64, String> {
+        let mut value = self.product()?;
+        if self.assembler.legacy {
+            // a - b - c is a - (b - c) for the older assembler
+            return if self.accept('+') {
+                Ok(value.wrapping_add(self.sum()?))
+            } else if self.accept('-') {
+                Ok(value.wrapping_sub(self.sum()?))
+            } else {
+                Ok(value)
+            };
+        }
+        loop {
+            if self.accept('+') {
+                value = value.wrapping_add(self.product()?);
+            } else if self.accept('-') {
+                value = value.wrapping_sub(self.product()?);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<i64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.accept('*') {
+                value = value.wrapping_mul(self.unary()?);
+            } else if self.accept('/') {
+                let divisor = self.unary()?;
+                if divisor == 0 {
+                    return Err("division by zero".to_string());
+                }
+                value = value.wrapping_div(divisor);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        if self.accept('-') {
+            Ok(self.unary()?.wrapping_neg())
+        } else if self.accept('+') {
+            self.unary()
+        } else if self.accept('~') {
+            Ok(!self.unary()?)
+        } else if self.accept('(') {
+            let value = self.or()?;
+            if !self.accept(')') {
+                return Err("missing ')'".to_string());
+            }
+            Ok(value)
+        } else {
+            self.value()
+        }
+    }
+
+    fn value(&mut self) -> Result<i64, String> {
+        self.skip_spaces();
+        let start = self.pos;
+        let (radix, digits): (u32, fn(char) -> bool) = match self.chars.get(self.pos) {
+            Some('#') => (16, |c| c.is_ascii_hexdigit()),
+            Some('$') => (2, |c| c == '0' || c == '1' || c == '.'),
+            Some('@') => (8, |c| c.is_digit(8)),
+            Some(c) if c.is_ascii_digit() => (10, |c| c.is_ascii_digit()),
+            Some(c) if c.is_alphanumeric() || *c == '_' || *c == '.' || *c == '?' => {
+                return self.symbol();
+            }
+            Some(c) => return Err(format!("unexpected '{}'", c)),
+            None => return Err("missing value".to_string()),
+        };
+
+        if radix != 10 {
+            self.pos += 1;
+        }
+        let number_start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| digits(*c)) {
+            self.pos += 1;
+        }
+        let number: String = self.chars[number_start..self.pos]
+            .iter()
+            .map(|c| if *c == '.' { '0' } else { *c })
+            .collect();
+        let text: String = self.chars[start..self.pos].iter().collect();
+        i64::from_str_radix(&number, radix).map_err(|_| format!("invalid number '{}'", text))
+    }
+
+    fn symbol(&mut self) -> Result<i64, String> {
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '.' || *c == '?')
+        {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        if name == "." || name == "?" {
+            return Ok(self.assembler.address as i64);
+        }
+        self.assembler.lookup(&name)
+    }
+}
@@ -0,0 +1,139 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+// The sound of the machine is a buzzer that sounds while the sound timer is
+// non-zero. By default it is a plain tone, XO-CHIP ROMs can instead load a
+// pattern of 128 one bit samples that is played over and over, at a rate
+// set by the pitch register
+
+// The pattern buffer is 16 bytes, the most significant bit plays first
+pub const PATTERN_SIZE: usize = 16;
+const PATTERN_BITS: f32 = (PATTERN_SIZE * 8) as f32;
+// The pitch register value that plays the pattern at 4000 bits per second
+pub const DEFAULT_PITCH: u8 = 64;
+
+// The rate in bits per second at which a pitch register value
+// plays the pattern
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    // The value of the wave, between -1 and 1, at a phase between 0 and 1
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Waveform, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!(
+                "unknown waveform '{}', expected one of square, triangle, sawtooth, sine",
+                s
+            )),
+        }
+    }
+}
+
+// What the buzzer sounds like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    // In Hz
+    pub frequency: f32,
+    pub waveform: Waveform,
+    // Between 0 (silent) and 1 (full scale)
+    pub volume: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Tone {
+        Tone {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+        }
+    }
+}
+
+// Turns the sound state of the machine into samples for an audio output
+// of any rate
+pub struct Synth {
+    // The XO-CHIP audio pattern, the tone plays while there is none
+    pub pattern: Option<[u8; PATTERN_SIZE]>,
+    pub pitch: u8,
+    pub tone: Tone,
+    // Whether the sound timer is non-zero
+    pub playing: bool,
+    sample_rate: u32,
+    // How far along the tone or pattern the output is, between 0 and 1
+    phase: f32,
+}
+
+impl Synth {
+    pub fn new(tone: Tone, sample_rate: u32) -> Synth {
+        Synth {
+            pattern: None,
+            pitch: DEFAULT_PITCH,
+            tone,
+            playing: false,
+            sample_rate,
+            phase: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // The next sample of the output, silence while not playing
+    pub fn next_sample(&mut self) -> f32 {
+        if !self.playing {
+            // Start every sound from the beginning
+            self.phase = 0.0;
+            return 0.0;
+        }
+
+        let (value, rate) = match self.pattern {
+            Some(pattern) => {
+                let bit = (self.phase * PATTERN_BITS) as usize;
+                let value = if (pattern[bit / 8] >> (7 - bit % 8)) & 0x1 == 1 {
+                    1.0
+                } else {
+                    -1.0
+                };
+                (value, pattern_rate(self.pitch) / PATTERN_BITS)
+            }
+            None => (self.tone.waveform.sample(self.phase), self.tone.frequency),
+        };
+
+        self.phase = (self.phase + rate / self.sample_rate as f32).fract();
+
+        value * self.tone.volume
+    }
+}
@@ -0,0 +1,114 @@
+use chip8::headless::{self, EXIT_EMULATOR_ERROR, EXIT_USAGE};
+use chip8::movie::{Movie, MoviePlayer};
+use chip8::trace::Trace;
+use chip8::Chip8;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process;
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [options] <path_to_rom>", program);
+    eprintln!(
+        "Example: {} --frames 120 --pbm ibm.pbm games/IBM.ch8",
+        program
+    );
+    eprintln!();
+    eprintln!("Runs a ROM without a window, then prints the registers and the screen.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --quirks <preset>        legacy, vip, chip48 or schip (default: legacy)");
+    eprintln!(
+        "  --ipf <n>                instructions per 60 Hz frame (default: {})",
+        chip8::DEFAULT_INSTRUCTIONS_PER_FRAME
+    );
+    eprintln!("  --seed <n>               seed for the random number generator (default: 0)");
+    eprintln!(
+        "  --frames <n>             number of frames to run (default: {})",
+        headless::DEFAULT_FRAMES
+    );
+    eprintln!("  --instructions <n>       stop after this many instructions");
+    eprintln!(
+        "  --press <frame:key[:n]>  hold a hex key from a frame on for n frames (default: 1)"
+    );
+    eprintln!("  --play <file>            feed the keypad from a movie file");
+    eprintln!("  --pbm <file>             write the screen as a PBM image");
+    eprintln!("  --ascii                  print the screen as text (default without --pbm)");
+    eprintln!("  --trace <file>           log every executed instruction to a file");
+    eprintln!("  --trace-range <from-to>  only log instructions at these hex addresses");
+    eprintln!("  --trace-count <n>        stop logging after n instructions");
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    process::exit(EXIT_USAGE);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let options = match headless::parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            print_usage(&args[0]);
+            process::exit(EXIT_USAGE);
+        }
+    };
+
+    let rom = fs::read(&options.rom)
+        .unwrap_or_else(|e| fail(format!("could not read ROM file '{}': {}", options.rom, e)));
+
+    let mut chip8 = Chip8::new(options.quirks);
+    chip8.instructions_per_frame = options.instructions_per_frame;
+    chip8.cpu.seed_rng(options.seed);
+    if let Err(e) = chip8.load_rom(&rom) {
+        fail(format!("could not load ROM file '{}': {}", options.rom, e));
+    }
+
+    if let Some(ref path) = options.trace {
+        let file = File::create(path)
+            .unwrap_or_else(|e| fail(format!("could not create trace file '{}': {}", path, e)));
+        let mut trace = Trace::new(Box::new(BufWriter::new(file)));
+        if let Some(ref range) = options.trace_range {
+            trace.set_range(range.clone());
+        }
+        if let Some(count) = options.trace_count {
+            trace.set_limit(count);
+        }
+        chip8.trace = Some(trace);
+    }
+
+    // A movie brings its own settings, just like in the window
+    let mut player = options.movie.as_ref().map(|path| {
+        let movie = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Movie::parse(&text))
+            .unwrap_or_else(|e| fail(format!("could not read movie file '{}': {}", path, e)));
+        if let Err(e) = movie.apply_settings(&mut chip8) {
+            fail(format!("could not play movie file '{}': {}", path, e));
+        }
+        MoviePlayer::new(movie)
+    });
+
+    let (frames, result) = headless::run(&mut chip8, &options, player.as_mut());
+
+    if let Some(ref mut trace) = chip8.trace {
+        if let Err(e) = trace.finish() {
+            fail(format!("could not write trace file: {}", e));
+        }
+    }
+
+    print!("{}", headless::report(&chip8, frames, options.ascii));
+    if let Some(ref path) = options.pbm {
+        if let Err(e) = fs::write(path, chip8.bus.display.to_pbm()) {
+            fail(format!("could not write '{}': {}", path, e));
+        }
+    }
+
+    if let Err(e) = result {
+        eprintln!("Emulator halted: {}", e);
+        process::exit(EXIT_EMULATOR_ERROR);
+    }
+}
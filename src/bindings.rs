@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+// Which keys of the host keyboard and which gamepad controls press which
+// keys of the hex keypad. Host keys go by the names the window uses for
+// them (Key1, Q, Numpad7, Up, Space, ...), in any case. Gamepad buttons go
+// by their names as well (DPadUp, South, Start, ...), and each direction of
+// a stick by the name of its axis and a sign (LeftStickX+, LeftStickY-).
+// Keys and controls without a binding do nothing at all.
+//
+// A bindings file starts from one of the built-in layouts and changes the
+// keys it lists, each to any number of host keys. Gamepads are bound in
+// sections of their own, [pad] for every pad or [pad1], [pad2], ... for
+// the pads in the order they are first used. A pad section starts out
+// empty, pads that are not given one use the default pad bindings:
+//
+//   # PONG2 with two pads
+//   layout = "qwerty"
+//
+//   [keys]
+//   1 = ["Key1", "Up"]
+//   4 = ["Q", "Down"]
+//   F = []
+//
+//   [pad1]
+//   1 = ["DPadUp", "LeftStickY+"]
+//   4 = ["DPadDown", "LeftStickY-"]
+//
+//   [pad2]
+//   C = ["DPadUp", "LeftStickY+"]
+//   D = ["DPadDown", "LeftStickY-"]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bindings {
+    // Host key names in lower case to hex keys
+    keys: HashMap<String, u8>,
+    // Gamepad control names in lower case to hex keys, for every pad and
+    // for the players that have their own
+    pad: HashMap<String, u8>,
+    players: HashMap<usize, HashMap<String, u8>>,
+}
+
+// The built-in layouts, the host key for each hex key from 0 to F. Both
+// keyboard layouts use the four by four block under 1 to 4, the same keys
+// by position
+const QWERTY: [&str; 16] = [
+    "X", "Key1", "Key2", "Key3", "Q", "W", "E", "A", "S", "D", "Z", "C", "Key4", "R", "F", "V",
+];
+const AZERTY: [&str; 16] = [
+    "X", "Key1", "Key2", "Key3", "A", "Z", "E", "Q", "S", "D", "W", "C", "Key4", "R", "F", "V",
+];
+// The numbers on the numeric keypad press the same numbers, like on the
+// keypad of the COSMAC VIP. C to F are the four operators, A and B are
+// Enter and the decimal point
+const NUMPAD: [&str; 16] = [
+    "Numpad0",
+    "Numpad1",
+    "Numpad2",
+    "Numpad3",
+    "Numpad4",
+    "Numpad5",
+    "Numpad6",
+    "Numpad7",
+    "Numpad8",
+    "Numpad9",
+    "NumpadEnter",
+    "NumpadDecimal",
+    "NumpadDivide",
+    "NumpadMultiply",
+    "NumpadSubtract",
+    "NumpadAdd",
+];
+
+pub const LAYOUTS: [&str; 3] = ["qwerty", "azerty", "numpad"];
+
+// Most games move with the keys around 5 and use 5 itself for the rest, so
+// the D-pad and the left stick press those
+const PAD: [(&str, u8); 9] = [
+    ("DPadUp", 0x2),
+    ("DPadDown", 0x8),
+    ("DPadLeft", 0x4),
+    ("DPadRight", 0x6),
+    ("LeftStickY+", 0x2),
+    ("LeftStickY-", 0x8),
+    ("LeftStickX-", 0x4),
+    ("LeftStickX+", 0x6),
+    ("South", 0x5),
+];
+
+// Where in a bindings file the lines go
+enum Section {
+    Keys,
+    Pad,
+    Player(usize),
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings::layout("qwerty").unwrap()
+    }
+
+    pub fn layout(name: &str) -> Result<Bindings, String> {
+        let hosts = match name.to_lowercase().as_str() {
+            "qwerty" => QWERTY,
+            "azerty" => AZERTY,
+            "numpad" => NUMPAD,
+            _ => {
+                return Err(format!(
+                    "unknown layout '{}', expected one of {}",
+                    name,
+                    LAYOUTS.join(", ")
+                ))
+            }
+        };
+
+        let mut bindings = Bindings {
+            keys: HashMap::new(),
+            pad: HashMap::new(),
+            players: HashMap::new(),
+        };
+        for (key, host) in hosts.iter().enumerate() {
+            bind(&mut bindings.keys, host, key as u8);
+        }
+        for (control, key) in PAD.iter() {
+            bind(&mut bindings.pad, control, *key);
+        }
+        Ok(bindings)
+    }
+
+    // Reads a bindings file
+    pub fn parse(text: &str) -> Result<Bindings, String> {
+        let mut bindings = Bindings::new();
+        bindings.update(text)?;
+        Ok(bindings)
+    }
+
+    // Applies a bindings file on top of these bindings, errors name the
+    // line they are on
+    pub fn update(&mut self, text: &str) -> Result<(), String> {
+        let mut section = Section::Keys;
+        let mut layout = None;
+        let mut changes = Vec::new();
+        let mut pad = None;
+        let mut players = HashMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", idx + 1, message);
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                section = match name.trim() {
+                    "keys" => Section::Keys,
+                    "pad" => {
+                        pad.get_or_insert_with(HashMap::new);
+                        Section::Pad
+                    }
+                    name => match name.strip_prefix("pad").map(str::parse) {
+                        Some(Ok(player)) if player > 0 => {
+                            players.entry(player).or_insert_with(HashMap::new);
+                            Section::Player(player)
+                        }
+                        _ => return Err(error(format!("unknown section [{}]", name))),
+                    },
+                };
+                continue;
+            }
+
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected NAME = VALUE, found '{}'", line)))?;
+            let (name, value) = (name.trim(), value.trim());
+            if name == "layout" {
+                layout = Some(Bindings::layout(&unquote(value).map_err(error)?).map_err(error)?);
+                continue;
+            }
+
+            let key = match u8::from_str_radix(name, 16) {
+                Ok(key) if key <= 0xF => key,
+                _ => return Err(error(format!("'{}' is not a key from 0 to F", name))),
+            };
+            let hosts = if let Some(list) = value.strip_prefix('[') {
+                let list = list
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("a list has to end with ]".to_string()))?;
+                list.split(',')
+                    .map(str::trim)
+                    .filter(|host| !host.is_empty())
+                    .map(unquote)
+                    .collect::<Result<Vec<String>, String>>()
+                    .map_err(error)?
+            } else {
+                vec![unquote(value).map_err(error)?]
+            };
+
+            let controls = match section {
+                Section::Keys => {
+                    changes.push((key, hosts));
+                    continue;
+                }
+                Section::Pad => pad.get_or_insert_with(HashMap::new),
+                Section::Player(player) => players.entry(player).or_insert_with(HashMap::new),
+            };
+            for control in hosts {
+                bind(controls, &control, key);
+            }
+        }
+
+        // The layout comes first wherever it is in the file
+        if let Some(layout) = layout {
+            self.keys = layout.keys;
+        }
+        for (key, hosts) in changes {
+            self.keys.retain(|_, bound| *bound != key);
+            for host in hosts {
+                bind(&mut self.keys, &host, key);
+            }
+        }
+        if let Some(pad) = pad {
+            self.pad = pad;
+        }
+        self.players.extend(players);
+        Ok(())
+    }
+
+    // The hex key a host key presses, if any
+    pub fn key(&self, host: &str) -> Option<u8> {
+        self.keys.get(&host.to_lowercase()).copied()
+    }
+
+    // The hex key a control of a gamepad presses, if any. Players are
+    // numbered from 1
+    pub fn pad_key(&self, player: usize, control: &str) -> Option<u8> {
+        self.players
+            .get(&player)
+            .unwrap_or(&self.pad)
+            .get(&control.to_lowercase())
+            .copied()
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings::new()
+    }
+}
+
+fn bind(controls: &mut HashMap<String, u8>, name: &str, key: u8) {
+    controls.insert(name.to_lowercase(), key);
+}
+
+fn unquote(value: &str) -> Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .ok_or_else(|| format!("expected a name in quotes, found '{}'", value))
+}
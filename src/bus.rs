@@ -1,6 +1,6 @@
-use super::display::Display;
-use super::keypad::Keypad;
-use super::memory::RAM;
+use crate::display::Display;
+use crate::keypad::Keypad;
+use crate::memory::RAM;
 
 pub struct Bus {
     pub ram: RAM,
@@ -17,3 +17,9 @@ impl Bus {
         }
     }
 }
+
+impl Default for Bus {
+    fn default() -> Bus {
+        Bus::new()
+    }
+}
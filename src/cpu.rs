@@ -0,0 +1,552 @@
+use crate::audio::{DEFAULT_PITCH, PATTERN_SIZE};
+use crate::bus::Bus;
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+use crate::memory::{BIG_FONT_ADDRESS, FONT_ADDRESS};
+use crate::quirks::Quirks;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::fmt;
+
+// The program will always start at 0x200 (512)
+// Since the rest is reserved for the interpreter
+pub const PROGRAM_START: u16 = 0x200;
+
+// What happened to the machine after processing a single operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    // The operation was executed and pc moved on
+    Executed,
+    // FX0A is still waiting for a key, see KeyWait
+    WaitingForKey,
+    // DXYN is held back until the next frame by the display wait quirk
+    WaitingForDisplay,
+    // 00FD stopped the interpreter, the same operation runs again next step
+    Exited,
+}
+
+// What FX0A is waiting for. The cpu stays on the FX0A and runs nothing
+// else until the wait is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWait {
+    // A key going down, it is stored in Vx
+    Press { x: u8 },
+    // With the key release quirk, the key that went down going up again
+    Release { x: u8, key: u8 },
+}
+
+pub struct CPU {
+    // General purpose registers
+    pub gp: [u8; 16],
+    // Program counter register
+    pub pc: u16,
+    // index register
+    pub i: u16,
+
+    // Stack
+    pub stack: [u16; 16],
+    // Stack pointer register
+    pub sp: u16,
+
+    // Delay timer register
+    pub dt: u8,
+    // Sound timer register
+    pub st: u8,
+
+    // XO-CHIP audio pattern, until F002 loads one the plain buzzer is used
+    pub audio_pattern: Option<[u8; PATTERN_SIZE]>,
+    // XO-CHIP pitch register, the playback rate of the audio pattern
+    pub pitch: u8,
+
+    // SUPER-CHIP user flags (the HP-48 RPL flags) for FX75 and FX85
+    pub rpl: [u8; 16],
+
+    // The current operation: operand
+    pub operand: u16,
+
+    // Which interpretation of the ambiguous instructions to use
+    pub quirks: Quirks,
+    // Set by every timer update, a draw with the display wait quirk
+    // consumes it
+    pub vblank: bool,
+    // Set while FX0A waits for a key
+    pub key_wait: Option<KeyWait>,
+    // Where CXNN gets its random numbers from, any rand generator can be
+    // plugged in. Seed it for runs that can be reproduced
+    pub rng: Box<dyn RngCore>,
+}
+
+impl CPU {
+    pub fn new(quirks: Quirks) -> CPU {
+        CPU {
+            gp: [0; 16],
+            i: 0,
+            pc: PROGRAM_START,
+            stack: [0; 16],
+            sp: 0,
+            dt: 0,
+            st: 0,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rpl: [0; 16],
+            operand: 0,
+            quirks,
+            vblank: true,
+            key_wait: None,
+            rng: Box::new(StdRng::from_entropy()),
+        }
+    }
+
+    // Make CXNN produce the same numbers every run
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
+    }
+
+    pub fn update_timers(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+
+        if self.st > 0 {
+            self.st -= 1;
+        }
+
+        self.vblank = true;
+    }
+
+    // Continue somewhere else, from the debugger. A waiting FX0A is given up
+    pub fn set_pc(&mut self, address: u16) {
+        self.pc = address;
+        self.key_wait = None;
+    }
+
+    // Read the big endian word at the given address
+    fn fetch(&self, bus: &Bus, address: u16) -> Result<u16, Chip8Error> {
+        // Some cool bitwise manipulation to transform two u8s into a u16
+        // see https://stackoverflow.com/questions/50243866/how-do-i-convert-two-u8-primitives-into-a-u16-primitive
+        let address = address as usize;
+        Ok(((bus.ram.read_byte_from_ram(address)? as u16) << 8)
+            | bus.ram.read_byte_from_ram(address + 1)? as u16)
+    }
+
+    // Skip the next instruction, F000 NNNN is two words long
+    fn skip(&mut self, bus: &Bus) -> Result<(), Chip8Error> {
+        let words = if self.fetch(bus, self.pc)? == 0xF000 {
+            2
+        } else {
+            1
+        };
+        self.pc = self.pc.wrapping_add(2 * words);
+        Ok(())
+    }
+
+    // Check the keypad for what FX0A is waiting for, and finish the FX0A
+    // once it is there
+    fn wait_for_key(&mut self, wait: KeyWait, bus: &mut Bus) -> StepOutcome {
+        let (x, key) = match wait {
+            KeyWait::Press { x } => match bus.keypad.take_press() {
+                Some(key) if self.quirks.key_release => {
+                    self.key_wait = Some(KeyWait::Release { x, key });
+                    return StepOutcome::WaitingForKey;
+                }
+                Some(key) => (x, key),
+                None => return StepOutcome::WaitingForKey,
+            },
+            KeyWait::Release { key, .. } if bus.keypad.is_key_down(key as usize) => {
+                return StepOutcome::WaitingForKey
+            }
+            KeyWait::Release { x, key } => (x, key),
+        };
+
+        self.gp[x as usize] = key;
+        self.key_wait = None;
+        self.pc = self.pc.wrapping_add(2);
+        StepOutcome::Executed
+    }
+
+    pub fn process_operation(&mut self, bus: &mut Bus) -> Result<StepOutcome, Chip8Error> {
+        // A waiting FX0A is not fetched again, only the keypad is checked
+        if let Some(wait) = self.key_wait {
+            return Ok(self.wait_for_key(wait, bus));
+        }
+
+        self.operand = self.fetch(bus, self.pc)?;
+        let op_pc = self.pc;
+
+        // Decode the operation, words that are not an instruction stop the cpu
+        let instruction = Instruction::decode(self.operand).ok_or(Chip8Error::UnknownOpcode {
+            pc: op_pc,
+            opcode: self.operand,
+        })?;
+
+        // Increment the counter
+        self.pc = self.pc.wrapping_add(2);
+
+        let mut outcome = StepOutcome::Executed;
+
+        match instruction {
+            Instruction::Scd(n) => {
+                // Scroll the screen down n lines
+                bus.display.scroll_down(n as usize);
+            }
+            Instruction::Scu(n) => {
+                // Scroll the screen up n lines
+                bus.display.scroll_up(n as usize);
+            }
+            Instruction::Scr => {
+                // Scroll the screen right 4 pixels
+                bus.display.scroll_right(4);
+            }
+            Instruction::Scl => {
+                // Scroll the screen left 4 pixels
+                bus.display.scroll_left(4);
+            }
+            Instruction::Exit => {
+                // Exit the interpreter, we stay on this operation
+                self.pc = self.pc.wrapping_sub(2);
+                outcome = StepOutcome::Exited;
+            }
+            Instruction::Low => {
+                // Switch to low resolution
+                bus.display.set_hires(false);
+            }
+            Instruction::High => {
+                // Switch to high resolution
+                bus.display.set_hires(true);
+            }
+            Instruction::Cls => {
+                // Clear the screen
+                bus.display.cls();
+            }
+            Instruction::Ret => {
+                // Return from a subroutine
+                if self.sp == 0 {
+                    return Err(Chip8Error::StackUnderflow { pc: op_pc });
+                }
+                self.sp -= 1;
+                self.pc = self.stack[self.sp as usize];
+            }
+            Instruction::Jp(nnn) => {
+                // Jump to location nnn
+                self.pc = nnn;
+            }
+            Instruction::Call(nnn) => {
+                // Call subroutine at nnn
+                if self.sp as usize >= self.stack.len() {
+                    return Err(Chip8Error::StackOverflow { pc: op_pc });
+                }
+                self.stack[self.sp as usize] = self.pc;
+                self.sp += 1;
+                self.pc = nnn;
+            }
+            Instruction::SeByte { x, kk } => {
+                // Increment the program counter by two 'words'
+                // if Vx == kk
+                if self.gp[x as usize] == kk {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::SneByte { x, kk } => {
+                // Increment the program counter by two 'words'
+                // if Vx != kk
+                if self.gp[x as usize] != kk {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::SeReg { x, y } => {
+                // Increment the program counter by two 'words'
+                // if Vx != Vy
+                if self.gp[x as usize] == self.gp[y as usize] {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::Save { x, y } => {
+                // Save Vx through Vy in memory starting at I,
+                // in reverse order if x > y
+                for (offset, idx) in register_range(x as usize, y as usize).enumerate() {
+                    bus.ram
+                        .write_byte_to_ram(self.i as usize + offset, self.gp[idx])?;
+                }
+            }
+            Instruction::Load { x, y } => {
+                // Load Vx through Vy from memory starting at I,
+                // in reverse order if x > y
+                for (offset, idx) in register_range(x as usize, y as usize).enumerate() {
+                    self.gp[idx] = bus.ram.read_byte_from_ram(self.i as usize + offset)?;
+                }
+            }
+            Instruction::LdByte { x, kk } => {
+                // set the Vx register to kk
+                self.gp[x as usize] = kk;
+            }
+            Instruction::AddByte { x, kk } => {
+                // add the Vx register and kk together
+                self.gp[x as usize] = self.gp[x as usize].wrapping_add(kk);
+            }
+            Instruction::LdReg { x, y } => {
+                // set the Vx register to Vy
+                self.gp[x as usize] = self.gp[y as usize];
+            }
+            Instruction::Or { x, y } => {
+                // OR the Vx and Vy
+                self.gp[x as usize] |= self.gp[y as usize];
+                if self.quirks.vf_reset {
+                    self.gp[0xF] = 0;
+                }
+            }
+            Instruction::And { x, y } => {
+                // AND the Vx and Vy
+                self.gp[x as usize] &= self.gp[y as usize];
+                if self.quirks.vf_reset {
+                    self.gp[0xF] = 0;
+                }
+            }
+            Instruction::Xor { x, y } => {
+                // XOR the Vx and Vy
+                self.gp[x as usize] ^= self.gp[y as usize];
+                if self.quirks.vf_reset {
+                    self.gp[0xF] = 0;
+                }
+            }
+            Instruction::AddReg { x, y } => {
+                // Add the Vy register to Vx
+                let new_value: u16 = self.gp[x as usize] as u16 + self.gp[y as usize] as u16;
+                self.gp[0xF] = if new_value > 0xFF { 1 } else { 0 };
+                self.gp[x as usize] = (new_value & 0xFF) as u8;
+            }
+            Instruction::Sub { x, y } => {
+                // Subtract the Vy from Vx
+                self.gp[0xF] = if self.gp[x as usize] > self.gp[y as usize] {
+                    1
+                } else {
+                    0
+                };
+                self.gp[x as usize] = self.gp[x as usize].wrapping_sub(self.gp[y as usize]);
+            }
+            Instruction::Shr { x, y } => {
+                // Shift Vx (or Vy) right by one, VF is set to the bit shifted out
+                let value = if self.quirks.shift {
+                    self.gp[x as usize]
+                } else {
+                    self.gp[y as usize]
+                };
+                self.gp[x as usize] = value >> 1;
+                self.gp[0xF] = value & 0x1;
+            }
+            Instruction::Subn { x, y } => {
+                // Subtract the Vy from Vx
+                self.gp[0xF] = if self.gp[x as usize] < self.gp[y as usize] {
+                    1
+                } else {
+                    0
+                };
+                self.gp[x as usize] = self.gp[y as usize].wrapping_sub(self.gp[x as usize]);
+            }
+            Instruction::Shl { x, y } => {
+                // Shift Vx (or Vy) left by one, VF is set to the bit shifted out
+                let value = if self.quirks.shift {
+                    self.gp[x as usize]
+                } else {
+                    self.gp[y as usize]
+                };
+                self.gp[x as usize] = value << 1;
+                self.gp[0xF] = value >> 7;
+            }
+            Instruction::SneReg { x, y } => {
+                // Skip next instruction if Vx != Vy
+                if self.gp[x as usize] != self.gp[y as usize] {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::LdI(nnn) => {
+                // Set I to nnn
+                self.i = nnn;
+            }
+            Instruction::JpV0(nnn) => {
+                // Jump to location nnn + V0 (or Vx, x being the highest
+                // nibble of nnn)
+                let offset = if self.quirks.jump {
+                    self.gp[(nnn >> 8) as usize]
+                } else {
+                    self.gp[0]
+                };
+                self.pc = nnn + offset as u16;
+            }
+            Instruction::Rnd { x, kk } => {
+                // Set Vx = random byte AND kk
+                self.gp[x as usize] = self.rng.gen::<u8>() & kk;
+            }
+            Instruction::Drw { .. }
+                if self.quirks.display_wait && !self.vblank && !bus.display.hires =>
+            {
+                // Try again once the next frame has started
+                self.pc = self.pc.wrapping_sub(2);
+                outcome = StepOutcome::WaitingForDisplay;
+            }
+            Instruction::Drw { x, y, n } => {
+                self.vblank = false;
+
+                // reset 0xF register
+                self.gp[0xF] = 0;
+
+                // Set a sprite in the graphics buffer, once for every
+                // selected plane with the sprite data for each plane
+                // following the previous one. DXY0 is a 16x16 sprite
+                // of two bytes per row
+                let len = if n == 0 { 32 } else { n as usize };
+                let (vx, vy) = (self.gp[x as usize] as usize, self.gp[y as usize] as usize);
+                let mut address = self.i as usize;
+                let mut has_collided = false;
+                for plane in bus.display.selected_planes() {
+                    // Get the sprite from memory
+                    let mut sprite = vec![0; len];
+                    for (idx, byte) in sprite.iter_mut().enumerate() {
+                        *byte = bus.ram.read_byte_from_ram(address + idx)?;
+                    }
+                    address += len;
+
+                    // Draw it
+                    has_collided |= if n == 0 {
+                        bus.display
+                            .draw_large(plane, vx, vy, &sprite, self.quirks.clipping)
+                    } else {
+                        bus.display
+                            .draw(plane, vx, vy, &sprite, self.quirks.clipping)
+                    };
+                }
+                self.gp[0xF] = if has_collided { 1 } else { 0 }
+            }
+            Instruction::Skp(x) => {
+                // Skip if the key in Vx is down
+                if bus.keypad.is_key_down(self.gp[x as usize] as usize & 0xF) {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::Sknp(x) => {
+                // Skip if the key in Vx is up
+                if !bus.keypad.is_key_down(self.gp[x as usize] as usize & 0xF) {
+                    self.skip(bus)?;
+                }
+            }
+            Instruction::LdLong => {
+                // Set I to the 16 bit address in the next word
+                self.i = self.fetch(bus, self.pc)?;
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::Plane(n) => {
+                // Select the planes to draw on
+                bus.display.select_planes(n);
+            }
+            Instruction::Audio => {
+                // Load the audio pattern from memory starting at I
+                let mut pattern = [0; PATTERN_SIZE];
+                for (idx, byte) in pattern.iter_mut().enumerate() {
+                    *byte = bus.ram.read_byte_from_ram(self.i as usize + idx)?;
+                }
+                self.audio_pattern = Some(pattern);
+            }
+            Instruction::LdVxDt(x) => {
+                // Set dt to Vx
+                self.gp[x as usize] = self.dt;
+            }
+            Instruction::LdVxK(x) => {
+                // Wait for a key to go down, one that is held from before
+                // does not count. We stay on this operation until it does
+                self.pc = self.pc.wrapping_sub(2);
+                let wait = KeyWait::Press { x };
+                self.key_wait = Some(wait);
+                outcome = self.wait_for_key(wait, bus);
+            }
+            Instruction::LdDtVx(x) => {
+                // Set dt to Vx
+                self.dt = self.gp[x as usize];
+            }
+            Instruction::LdStVx(x) => {
+                // Set st to Vx
+                self.st = self.gp[x as usize];
+            }
+            Instruction::AddI(x) => {
+                // Add I = I + Vx
+                self.i = self.i.wrapping_add(self.gp[x as usize] as u16);
+            }
+            Instruction::LdF(x) => {
+                // Set I = location of sprite (from Vx)
+                self.i = FONT_ADDRESS + (self.gp[x as usize] as u16 & 0xF) * 5;
+            }
+            Instruction::LdHf(x) => {
+                // Set I = location of large sprite (from Vx)
+                self.i = BIG_FONT_ADDRESS + (self.gp[x as usize] as u16 & 0xF) * 10;
+            }
+            Instruction::LdB(x) => {
+                // Store BCD representation of Vx in memory
+                // at locations I+{0,1,2}
+                let i = self.i as usize;
+                bus.ram.write_byte_to_ram(i, self.gp[x as usize] / 100)?;
+                bus.ram
+                    .write_byte_to_ram(i + 1, (self.gp[x as usize] / 10) % 10)?;
+                bus.ram.write_byte_to_ram(i + 2, self.gp[x as usize] % 10)?;
+            }
+            Instruction::LdIVx(x) => {
+                // Save V0 through Vx in memory starting at I
+                for idx in 0..=x as usize {
+                    bus.ram
+                        .write_byte_to_ram(self.i as usize + idx, self.gp[idx])?;
+                }
+                if self.quirks.load_store {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction::LdVxI(x) => {
+                // Load V0 through Vx in memory starting at I
+                for idx in 0..=x as usize {
+                    self.gp[idx] = bus.ram.read_byte_from_ram(self.i as usize + idx)?;
+                }
+                if self.quirks.load_store {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction::Pitch(x) => {
+                // Set the pitch register to Vx
+                self.pitch = self.gp[x as usize];
+            }
+            Instruction::LdRVx(x) => {
+                // Save V0 through Vx in the user flags
+                self.rpl[..=x as usize].copy_from_slice(&self.gp[..=x as usize]);
+            }
+            Instruction::LdVxR(x) => {
+                // Load V0 through Vx from the user flags
+                self.gp[..=x as usize].copy_from_slice(&self.rpl[..=x as usize]);
+            }
+        }
+
+        Ok(outcome)
+    }
+}
+
+impl Default for CPU {
+    fn default() -> CPU {
+        CPU::new(Quirks::default())
+    }
+}
+
+// The registers from x up to and including y, counting down if x > y
+fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+impl fmt::Debug for CPU {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\npc: {:#X}\n", self.pc)?;
+        write!(f, "vx: ")?;
+        for item in &self.gp {
+            write!(f, "{:#X} ", *item)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "i: {:#X}", self.i)?;
+        writeln!(f, "operand: {:#X}", self.operand)
+    }
+}
@@ -0,0 +1,531 @@
+use crate::cpu::StepOutcome;
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+use crate::Chip8;
+use std::fmt;
+
+// An interactive debugger that sits between a frontend and the machine. The
+// frontend feeds it command lines and runs frames through it instead of
+// through Chip8::run_frame, so that it can stop on breakpoints in the middle
+// of a frame. Everything it prints is collected until the frontend takes it
+//
+//   break 2A4        stop before the instruction at 2A4 runs
+//   breakop D??F     stop before any DXYF
+//   step 3           run three instructions
+//   x 300 20         show 32 bytes from 300
+//
+// All numbers are hexadecimal, with or without a # or 0x in front.
+
+// The instructions shown before and after pc by list
+const LIST_BEFORE: u16 = 4;
+const LIST_AFTER: u16 = 6;
+// The bytes shown by x unless told otherwise, and per line
+const EXAMINE_BYTES: usize = 16;
+const EXAMINE_ROW: usize = 8;
+
+const HELP: &str = "\
+Commands, numbers are hexadecimal:
+  step [n], s        run n instructions (default: 1)
+  continue, c        run until a breakpoint is hit
+  pause              stop running
+  break <addr>, b    stop before the instruction at addr
+  breakop <pattern>  stop before opcodes like D??F, ? matches any nibble
+  delete [n], d      remove breakpoint n, or all of them
+  breaks             list the breakpoints
+  regs, r            show the registers, timers and stack
+  x <addr> [len]     show len bytes of memory (default: 10)
+  poke <addr> <b>..  write bytes to memory
+  set <reg> <value>  set V0-VF, I, PC, SP, DT or ST
+  list [addr], l     disassemble around pc or addr
+  quit, q            close the emulator
+An empty line repeats the last command.
+";
+
+// Where the debugger stops the machine, before the instruction runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Address(u16),
+    // Opcodes that have value in the bits of mask
+    Opcode { mask: u16, value: u16 },
+}
+
+impl Breakpoint {
+    // A pattern is four nibbles, each a hex digit or ? for any value
+    pub fn pattern(text: &str) -> Result<Breakpoint, String> {
+        let invalid = || {
+            format!(
+                "invalid opcode pattern '{}', expected something like D??F",
+                text
+            )
+        };
+        if text.chars().count() != 4 {
+            return Err(invalid());
+        }
+
+        let mut mask = 0;
+        let mut value = 0;
+        for c in text.chars() {
+            mask <<= 4;
+            value <<= 4;
+            if c != '?' {
+                mask |= 0xF;
+                value |= c.to_digit(16).ok_or_else(invalid)? as u16;
+            }
+        }
+        Ok(Breakpoint::Opcode { mask, value })
+    }
+
+    fn matches(&self, pc: u16, opcode: u16) -> bool {
+        match *self {
+            Breakpoint::Address(address) => pc == address,
+            Breakpoint::Opcode { mask, value } => opcode & mask == value,
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Breakpoint::Address(address) => write!(f, "at {:04X}", address),
+            Breakpoint::Opcode { mask, value } => {
+                write!(f, "on ")?;
+                for shift in [12, 8, 4, 0].iter() {
+                    if (mask >> shift) & 0xF == 0 {
+                        write!(f, "?")?;
+                    } else {
+                        write!(f, "{:X}", (value >> shift) & 0xF)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct Debugger {
+    // While paused the frontend runs no frames at all
+    pub paused: bool,
+    // Set by the quit command, the frontend closes once it sees it
+    pub quit: bool,
+    breakpoints: Vec<Breakpoint>,
+    // How many instructions of the current frame have run, a frame that
+    // is cut short by a breakpoint picks up where it stopped
+    cycle: u32,
+    // Continuing from a breakpoint runs the instruction it stopped at,
+    // instead of stopping on it again
+    resuming: bool,
+    last_command: String,
+    output: String,
+}
+
+impl Debugger {
+    // The machine starts out paused, so that breakpoints can be set
+    // before the first instruction runs
+    pub fn new() -> Debugger {
+        Debugger {
+            paused: true,
+            quit: false,
+            breakpoints: Vec::new(),
+            cycle: 0,
+            resuming: false,
+            last_command: String::new(),
+            output: String::new(),
+        }
+    }
+
+    // Everything printed since the last call
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn pause(&mut self, chip8: &Chip8) {
+        if !self.paused {
+            self.paused = true;
+            self.print(format!("Paused at {:04X}", chip8.cpu.pc));
+            self.list(chip8, chip8.cpu.pc, 0, 1);
+        }
+    }
+
+    // Run again, starting with the instruction it stopped at
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.resuming = true;
+    }
+
+    // The index of the breakpoint, the one it already had if it was set
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        match self.breakpoints.iter().position(|b| *b == breakpoint) {
+            Some(idx) => idx,
+            None => {
+                self.breakpoints.push(breakpoint);
+                self.breakpoints.len() - 1
+            }
+        }
+    }
+
+    // False if there was no such breakpoint
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|b| *b != breakpoint);
+        self.breakpoints.len() != count
+    }
+
+    // True between frames, per frame input like the keypad of a movie
+    // should only be fed in then
+    pub fn at_frame_start(&self) -> bool {
+        self.cycle == 0
+    }
+
+    // Run the rest of the current frame, unless a breakpoint is hit first.
+    // The timers only tick once the whole frame has run
+    pub fn run_frame(&mut self, chip8: &mut Chip8) -> Result<StepOutcome, Chip8Error> {
+        loop {
+            if !self.resuming {
+                let pc = chip8.cpu.pc;
+                let opcode = word(chip8, pc);
+                if let Some(idx) = self.breakpoints.iter().position(|b| b.matches(pc, opcode)) {
+                    self.paused = true;
+                    self.print(format!("Breakpoint {} {}", idx, self.breakpoints[idx]));
+                    self.list(chip8, pc, 0, 1);
+                    return Ok(StepOutcome::Executed);
+                }
+            }
+            let outcome = self.step(chip8)?;
+            // An instruction that waits runs again, without stopping on it
+            // every time
+            self.resuming = matches!(
+                outcome,
+                StepOutcome::WaitingForKey | StepOutcome::WaitingForDisplay
+            );
+            if self.at_frame_start() {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    // Run a single instruction as part of the current frame
+    pub fn step(&mut self, chip8: &mut Chip8) -> Result<StepOutcome, Chip8Error> {
+        let outcome = chip8.step()?;
+        self.cycle += 1;
+        let frame_over = matches!(
+            outcome,
+            StepOutcome::WaitingForDisplay | StepOutcome::Exited
+        );
+        if frame_over || self.cycle >= chip8.instructions_per_frame {
+            chip8.end_frame();
+            self.cycle = 0;
+        }
+        Ok(outcome)
+    }
+
+    // Run one command line, errors of the machine while stepping are passed
+    // on so that the frontend halts just like it would without a debugger
+    pub fn execute(&mut self, chip8: &mut Chip8, line: &str) -> Result<(), Chip8Error> {
+        let line = if line.trim().is_empty() {
+            self.last_command.clone()
+        } else {
+            line.trim().to_string()
+        };
+        self.last_command = line.clone();
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Ok(()),
+        };
+        match self.command(chip8, command, args) {
+            Ok(()) => Ok(()),
+            Err(Error::Usage(message)) => {
+                self.print(format!("Error: {}", message));
+                Ok(())
+            }
+            Err(Error::Machine(e)) => Err(e),
+        }
+    }
+
+    fn command(&mut self, chip8: &mut Chip8, command: &str, args: &[&str]) -> Result<(), Error> {
+        match (command, args) {
+            ("help", []) | ("h", []) => self.print(HELP.trim_end()),
+            ("step", _) | ("s", _) if args.len() <= 1 => {
+                let count = match args.first() {
+                    Some(count) => number(count)?,
+                    None => 1,
+                };
+                self.paused = true;
+                self.step_and_list(chip8, count)?;
+            }
+            ("continue", []) | ("c", []) => self.resume(),
+            ("pause", []) => self.pause(chip8),
+            ("break", [address]) | ("b", [address]) => {
+                let breakpoint = Breakpoint::Address(address_of(address)?);
+                self.set_breakpoint(breakpoint);
+            }
+            ("breakop", [pattern]) => {
+                let breakpoint = Breakpoint::pattern(pattern)?;
+                self.set_breakpoint(breakpoint);
+            }
+            ("delete", []) | ("d", []) => self.breakpoints.clear(),
+            ("delete", [idx]) | ("d", [idx]) => {
+                let idx = number(idx)? as usize;
+                if idx >= self.breakpoints.len() {
+                    return Err(format!("there is no breakpoint {:X}", idx).into());
+                }
+                self.breakpoints.remove(idx);
+            }
+            ("breaks", []) => {
+                if self.breakpoints.is_empty() {
+                    self.print("No breakpoints");
+                }
+                let lines: Vec<String> = self
+                    .breakpoints
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, breakpoint)| format!("{:X}: {}", idx, breakpoint))
+                    .collect();
+                for line in lines {
+                    self.print(line);
+                }
+            }
+            ("regs", []) | ("r", []) => self.print(registers(chip8).trim_end()),
+            ("x", [address]) => self.examine(chip8, address_of(address)?, EXAMINE_BYTES)?,
+            ("x", [address, length]) => {
+                self.examine(chip8, address_of(address)?, number(length)? as usize)?
+            }
+            ("poke", [address, bytes @ ..]) if !bytes.is_empty() => {
+                let address = address_of(address)? as usize;
+                for (offset, byte) in bytes.iter().enumerate() {
+                    let value = number(byte)?;
+                    if value > 0xFF {
+                        return Err(format!("{:X} does not fit in a byte", value).into());
+                    }
+                    chip8
+                        .bus
+                        .ram
+                        .write_byte_to_ram(address + offset, value as u8)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            ("set", [register, value]) => set_register(chip8, register, number(value)?)?,
+            ("list", []) | ("l", []) => self.list(chip8, chip8.cpu.pc, LIST_BEFORE, LIST_AFTER),
+            ("list", [address]) | ("l", [address]) => {
+                self.list(chip8, address_of(address)?, LIST_BEFORE, LIST_AFTER)
+            }
+            ("quit", []) | ("q", []) => self.quit = true,
+            _ => {
+                return Err(format!(
+                    "unknown command '{}', type help for a list",
+                    args.iter()
+                        .fold(command.to_string(), |line, arg| line + " " + arg)
+                )
+                .into())
+            }
+        }
+        Ok(())
+    }
+
+    fn step_and_list(&mut self, chip8: &mut Chip8, count: u32) -> Result<(), Chip8Error> {
+        for _ in 0..count {
+            match self.step(chip8)? {
+                StepOutcome::WaitingForKey => {
+                    self.print("Waiting for a key");
+                    break;
+                }
+                StepOutcome::WaitingForDisplay => {
+                    self.print("Waiting for the next frame to draw");
+                    break;
+                }
+                StepOutcome::Exited => {
+                    self.print("The program has exited");
+                    break;
+                }
+                StepOutcome::Executed => {}
+            }
+        }
+        self.list(chip8, chip8.cpu.pc, 0, 1);
+        Ok(())
+    }
+
+    fn examine(&mut self, chip8: &Chip8, address: u16, length: usize) -> Result<(), Error> {
+        let memory = &chip8.bus.ram.memory;
+        let start = address as usize;
+        let end = (start + length).min(memory.len());
+        let lines: Vec<String> = memory[start..end]
+            .chunks(EXAMINE_ROW)
+            .enumerate()
+            .map(|(row, bytes)| {
+                let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                format!("{:04X}: {}", start + row * EXAMINE_ROW, hex.join(" "))
+            })
+            .collect();
+        for line in lines {
+            self.print(line);
+        }
+        Ok(())
+    }
+
+    // Disassemble the instructions around an address, marking pc with >
+    // and breakpoints with *. Instructions before it are only a guess,
+    // the words are decoded from a fixed distance back
+    fn list(&mut self, chip8: &Chip8, address: u16, before: u16, after: u16) {
+        let mut address = address.saturating_sub(before * 2);
+        for _ in 0..before + after {
+            let opcode = word(chip8, address);
+            let (text, size) = match Instruction::decode(opcode) {
+                Some(Instruction::LdLong) => (
+                    format!(
+                        "LD    I, LONG #{:04X}",
+                        word(chip8, address.wrapping_add(2))
+                    ),
+                    4,
+                ),
+                Some(instruction) => (instruction.to_string(), instruction.size()),
+                None => (format!("DW    #{:04X}", opcode), 2),
+            };
+            let current = if address == chip8.cpu.pc { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&Breakpoint::Address(address)) {
+                '*'
+            } else {
+                ' '
+            };
+            self.print(format!(
+                "{}{} {:04X}  {:04X}  {}",
+                current, breakpoint, address, opcode, text
+            ));
+            address = address.wrapping_add(size);
+        }
+    }
+
+    // Breakpoints that are already set keep their index
+    fn set_breakpoint(&mut self, breakpoint: Breakpoint) {
+        let count = self.breakpoints.len();
+        let idx = self.add_breakpoint(breakpoint);
+        if idx < count {
+            self.print(format!("Breakpoint {} {} is already set", idx, breakpoint));
+        } else {
+            self.print(format!("Breakpoint {} {}", idx, breakpoint));
+        }
+    }
+
+    fn print<S: AsRef<str>>(&mut self, text: S) {
+        self.output.push_str(text.as_ref());
+        self.output.push('\n');
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+// A command fails either because it was mistyped, or because the machine
+// ran into an error while stepping
+enum Error {
+    Usage(String),
+    Machine(Chip8Error),
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Usage(message)
+    }
+}
+
+impl From<Chip8Error> for Error {
+    fn from(e: Chip8Error) -> Error {
+        Error::Machine(e)
+    }
+}
+
+// The registers, timers and stack of the cpu
+pub fn registers(chip8: &Chip8) -> String {
+    let cpu = &chip8.cpu;
+    let registers: Vec<String> = cpu
+        .gp
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| format!("V{:X}: {:02X}", idx, value))
+        .collect();
+    let stack: Vec<String> = cpu.stack[..cpu.sp as usize]
+        .iter()
+        .map(|addr| format!("{:04X}", addr))
+        .collect();
+    format!(
+        "pc: {:04X} i: {:04X} sp: {:X} dt: {:02X} st: {:02X}\n{}\nstack: [{}]\n",
+        cpu.pc,
+        cpu.i,
+        cpu.sp,
+        cpu.dt,
+        cpu.st,
+        registers.join(" "),
+        stack.join(" ")
+    )
+}
+
+// The big endian word at an address, reads past the end of memory are 0
+fn word(chip8: &Chip8, address: u16) -> u16 {
+    let byte = |address: usize| chip8.bus.ram.memory.get(address).copied().unwrap_or(0);
+    (byte(address as usize) as u16) << 8 | byte(address as usize + 1) as u16
+}
+
+fn number(text: &str) -> Result<u32, String> {
+    let digits = text
+        .trim_start_matches('#')
+        .trim_start_matches("0x")
+        .trim_start_matches("0X");
+    u32::from_str_radix(digits, 16).map_err(|_| format!("invalid number '{}'", text))
+}
+
+fn address_of(text: &str) -> Result<u16, String> {
+    let value = number(text)?;
+    if value > 0xFFFF {
+        return Err(format!("address {:X} is outside of memory", value));
+    }
+    Ok(value as u16)
+}
+
+fn set_register(chip8: &mut Chip8, register: &str, value: u32) -> Result<(), String> {
+    let cpu = &mut chip8.cpu;
+    let too_large = |bits: u32| -> Result<(), String> {
+        if value >> bits != 0 {
+            Err(format!("{:X} does not fit in {}", value, register))
+        } else {
+            Ok(())
+        }
+    };
+
+    match register.to_uppercase().as_str() {
+        "I" => {
+            too_large(16)?;
+            cpu.i = value as u16;
+        }
+        "PC" => {
+            too_large(16)?;
+            cpu.set_pc(value as u16);
+        }
+        "SP" => {
+            if value as usize > cpu.stack.len() {
+                return Err(format!("{:X} does not fit in SP", value));
+            }
+            cpu.sp = value as u16;
+        }
+        "DT" => {
+            too_large(8)?;
+            cpu.dt = value as u8;
+        }
+        "ST" => {
+            too_large(8)?;
+            cpu.st = value as u8;
+        }
+        name => {
+            let idx = name
+                .strip_prefix('V')
+                .filter(|digit| digit.len() == 1)
+                .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                .ok_or_else(|| format!("unknown register '{}'", register))?;
+            too_large(8)?;
+            cpu.gp[idx as usize] = value as u8;
+        }
+    }
+    Ok(())
+}
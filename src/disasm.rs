@@ -0,0 +1,213 @@
+use crate::cpu::PROGRAM_START;
+use crate::instruction::Instruction;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+// A ROM is split into code and data by following the control flow from the
+// program start: everything that a jump, call, skip or fall through can
+// reach is code, the rest is data. Jump and call targets get L labels and
+// the addresses loaded into I get D labels, so that the output can be fed
+// back into an assembler
+//
+//   L200:
+//       LD    VA, #02         ; 0200  6A02
+//       LD    I, D2EA         ; 0202  A2EA
+//   ...
+//   D2EA:
+//       DB    #80             ; 02EA  #.......
+
+// One line of a disassembly, either an instruction or a byte of data
+pub struct Line {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub label: Option<String>,
+    pub text: String,
+    pub is_code: bool,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref label) = self.label {
+            writeln!(f, "{}:", label)?;
+        }
+
+        // Instructions show their raw word, data bytes their bit pattern
+        // which makes sprites easy to spot
+        let comment = if self.is_code {
+            self.bytes.iter().map(|b| format!("{:02X}", b)).collect()
+        } else {
+            (0..8)
+                .map(|bit| {
+                    if self.bytes[0] & (0x80 >> bit) != 0 {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        };
+        write!(
+            f,
+            "    {:<22}; {:04X}  {}",
+            self.text, self.address, comment
+        )
+    }
+}
+
+// Where the control flow can go after an instruction, and which addresses
+// it refers to that need a label
+struct Flow {
+    next: Vec<u16>,
+    jumps: Vec<u16>,
+    data: Vec<u16>,
+}
+
+fn flow(instruction: Instruction, next: u16, address: u16, following: u16) -> Flow {
+    let after = address.wrapping_add(instruction.size());
+    let mut flow = Flow {
+        next: vec![after],
+        jumps: Vec::new(),
+        data: Vec::new(),
+    };
+
+    match instruction {
+        // Return and exit end the flow
+        Instruction::Ret | Instruction::Exit => flow.next.clear(),
+        Instruction::Jp(addr) => {
+            flow.next = vec![addr];
+            flow.jumps.push(addr);
+        }
+        Instruction::Call(addr) => {
+            flow.next.push(addr);
+            flow.jumps.push(addr);
+        }
+        // Skips can land on either of the next two instructions
+        Instruction::SeByte { .. }
+        | Instruction::SneByte { .. }
+        | Instruction::SeReg { .. }
+        | Instruction::SneReg { .. }
+        | Instruction::Skp(_)
+        | Instruction::Sknp(_) => {
+            flow.next.push(after.wrapping_add(length(following)));
+        }
+        Instruction::LdI(addr) => flow.data.push(addr),
+        // The target of a computed jump is unknown
+        Instruction::JpV0(addr) => {
+            flow.next.clear();
+            flow.jumps.push(addr);
+        }
+        Instruction::LdLong => flow.data.push(next),
+        _ => {}
+    }
+    flow
+}
+
+// The length in bytes of the instruction that starts with this word
+fn length(opcode: u16) -> u16 {
+    Instruction::decode(opcode).map_or(2, |instruction| instruction.size())
+}
+
+// The text of an instruction, F000 also names the address in the next word
+fn text(instruction: Instruction, next: u16, label: &dyn Fn(u16) -> String) -> String {
+    let text = instruction.format(label);
+    if instruction == Instruction::LdLong {
+        format!("{} {}", text, label(next))
+    } else {
+        text
+    }
+}
+
+// Disassemble a ROM that is loaded at the program start
+pub fn disassemble(rom: &[u8]) -> Vec<Line> {
+    let start = PROGRAM_START as usize;
+    let end = start + rom.len();
+    let byte = |address: usize| -> u8 {
+        if address >= start && address < end {
+            rom[address - start]
+        } else {
+            0
+        }
+    };
+    let word = |address: u16| -> u16 {
+        ((byte(address as usize) as u16) << 8) | byte(address as usize + 1) as u16
+    };
+
+    // Follow every path from the program start to find the instructions
+    let mut code = BTreeSet::new();
+    let mut jumps = BTreeSet::new();
+    let mut data = BTreeSet::new();
+    let mut pending = vec![PROGRAM_START];
+    while let Some(address) = pending.pop() {
+        let index = address as usize;
+        if index < start || index + 1 >= end || code.contains(&address) {
+            continue;
+        }
+        let instruction = match Instruction::decode(word(address)) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        code.insert(address);
+
+        let next = word(address.wrapping_add(2));
+        let following = word(address.wrapping_add(instruction.size()));
+        let flow = flow(instruction, next, address, following);
+        pending.extend(flow.next);
+        jumps.extend(flow.jumps);
+        data.extend(flow.data);
+    }
+
+    // Only addresses that start a line can carry a label, anything else
+    // is printed as a number
+    let mut lines = Vec::new();
+    let mut starts = BTreeMap::new();
+    let mut address = start;
+    while address < end {
+        let length = if code.contains(&(address as u16)) {
+            length(word(address as u16)) as usize
+        } else {
+            1
+        };
+        starts.insert(address as u16, length);
+        address += length;
+    }
+    let name = |address: u16| -> Option<String> {
+        if !starts.contains_key(&address) {
+            None
+        } else if jumps.contains(&address) {
+            Some(format!("L{:03X}", address))
+        } else if data.contains(&address) {
+            Some(format!("D{:03X}", address))
+        } else {
+            None
+        }
+    };
+    let label = |address: u16| name(address).unwrap_or_else(|| format!("#{:03X}", address));
+
+    for (&address, &length) in &starts {
+        let bytes: Vec<u8> = (0..length).map(|i| byte(address as usize + i)).collect();
+        let is_code = code.contains(&address);
+        let text = match Instruction::decode(word(address)) {
+            Some(instruction) if is_code => {
+                text(instruction, word(address.wrapping_add(2)), &label)
+            }
+            _ => {
+                format!("DB    #{:02X}", bytes[0])
+            }
+        };
+        lines.push(Line {
+            address,
+            label: name(address).or_else(|| {
+                // The program start always gets a label, as the entry point
+                if address == PROGRAM_START {
+                    Some(format!("L{:03X}", address))
+                } else {
+                    None
+                }
+            }),
+            bytes,
+            text,
+            is_code,
+        });
+    }
+    lines
+}
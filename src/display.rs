@@ -0,0 +1,257 @@
+// Low resolution, the only mode of the original CHIP-8
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+// High resolution, added by SUPER-CHIP
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+const DISPLAY_SIZE: usize = HIRES_WIDTH * HIRES_HEIGHT;
+// The size of a low resolution pixel in the window
+pub const PIXEL_SIZE: usize = 20;
+
+// XO-CHIP has two bitplanes, each is one bit of a pixel
+pub const PLANES: [u8; 2] = [0b01, 0b10];
+// How Display::to_ascii draws each of the four pixel values
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+pub struct Display {
+    // Graphics gfx is 64 by 32 pixels, or 128 by 64 in high resolution
+    // A pixel is one of four colors, a bit for each of the two planes
+    // We can put it in a one dimensional array, the rows are as wide
+    // as the current resolution
+    pub gfx: [u8; DISPLAY_SIZE],
+    // Whether the SUPER-CHIP high resolution mode is on
+    pub hires: bool,
+    // The planes that drawing, clearing and scrolling work on
+    pub planes: u8,
+}
+
+impl Display {
+    pub fn new() -> Display {
+        Display {
+            gfx: [0; DISPLAY_SIZE],
+            hires: false,
+            planes: PLANES[0],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    // Select the planes to work on from a bitmask of both planes
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
+    }
+
+    // The selected planes, in the order in which they are drawn
+    pub fn selected_planes(&self) -> Vec<u8> {
+        PLANES
+            .iter()
+            .copied()
+            .filter(|plane| self.planes & plane != 0)
+            .collect()
+    }
+
+    // Clear the selected planes of the screen
+    pub fn cls(&mut self) {
+        let planes = self.planes;
+        self.gfx.iter_mut().for_each(|x| *x &= !planes);
+    }
+
+    // Switch between low and high resolution, which also clears the screen
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.gfx.iter_mut().for_each(|x| *x = 0);
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        self.gfx[x + y * self.width()]
+    }
+
+    // The screen as text, one line per row. Pixels are drawn by their
+    // planes: '.' for black, '#' for the first plane, '+' for the second
+    // and '@' for both
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.width() + 1) * self.height());
+        for row in 0..self.height() {
+            for col in 0..self.width() {
+                text.push(ASCII_PIXELS[self.get_pixel(col, row) as usize]);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    // The screen as a plain PBM image, any lit pixel is black
+    pub fn to_pbm(&self) -> String {
+        let mut text = format!("P1\n{} {}\n", self.width(), self.height());
+        for row in 0..self.height() {
+            let line: Vec<&str> = (0..self.width())
+                .map(|col| {
+                    if self.get_pixel(col, row) != 0 {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect();
+            text.push_str(&line.join(" "));
+            text.push('\n');
+        }
+        text
+    }
+
+    // Scroll the screen up by n lines, the bottom is filled with black
+    pub fn scroll_up(&mut self, n: usize) {
+        let (width, height) = (self.width(), self.height());
+        for row in 0..height {
+            for col in 0..width {
+                let from = if row + n < height {
+                    Some(col + (row + n) * width)
+                } else {
+                    None
+                };
+                self.shift_pixel(col + row * width, from);
+            }
+        }
+    }
+
+    // Scroll the screen down by n lines, the top is filled with black
+    pub fn scroll_down(&mut self, n: usize) {
+        let (width, height) = (self.width(), self.height());
+        for row in (0..height).rev() {
+            for col in 0..width {
+                let from = if row >= n {
+                    Some(col + (row - n) * width)
+                } else {
+                    None
+                };
+                self.shift_pixel(col + row * width, from);
+            }
+        }
+    }
+
+    // Scroll the screen left by n pixels, the right is filled with black
+    pub fn scroll_left(&mut self, n: usize) {
+        let (width, height) = (self.width(), self.height());
+        for row in 0..height {
+            for col in 0..width {
+                let from = if col + n < width {
+                    Some(col + n + row * width)
+                } else {
+                    None
+                };
+                self.shift_pixel(col + row * width, from);
+            }
+        }
+    }
+
+    // Scroll the screen right by n pixels, the left is filled with black
+    pub fn scroll_right(&mut self, n: usize) {
+        let (width, height) = (self.width(), self.height());
+        for row in 0..height {
+            for col in (0..width).rev() {
+                let from = if col >= n {
+                    Some(col - n + row * width)
+                } else {
+                    None
+                };
+                self.shift_pixel(col + row * width, from);
+            }
+        }
+    }
+
+    // Move the selected planes of a pixel to another index while scrolling,
+    // pixels scrolled in from outside of the screen are black
+    fn shift_pixel(&mut self, to: usize, from: Option<usize>) {
+        let value = from.map_or(0, |from| self.gfx[from]);
+        self.gfx[to] = (self.gfx[to] & !self.planes) | (value & self.planes);
+    }
+
+    // Draw a sprite on a plane of the screen at the given coordinates
+    // Each byte of the sprite is a row of 8 pixels
+    pub fn draw(&mut self, plane: u8, x: usize, y: usize, sprite: &[u8], clip: bool) -> bool {
+        let rows: Vec<u16> = sprite.iter().map(|byte| (*byte as u16) << 8).collect();
+        self.draw_rows(plane, x, y, &rows, 8, clip)
+    }
+
+    // Draw a SUPER-CHIP 16x16 sprite on a plane of the screen at the given
+    // coordinates. Each pair of bytes of the sprite is a row of 16 pixels
+    pub fn draw_large(&mut self, plane: u8, x: usize, y: usize, sprite: &[u8], clip: bool) -> bool {
+        let rows: Vec<u16> = sprite
+            .chunks(2)
+            .map(|pair| ((pair[0] as u16) << 8) | *pair.get(1).unwrap_or(&0) as u16)
+            .collect();
+        self.draw_rows(plane, x, y, &rows, 16, clip)
+    }
+
+    // Pixels that fall off the edge of the screen are either clipped
+    // or wrap around to the other side
+    fn draw_rows(
+        &mut self,
+        plane: u8,
+        x: usize,
+        y: usize,
+        rows: &[u16],
+        row_width: usize,
+        clip: bool,
+    ) -> bool {
+        let mut has_collided = false;
+        let (width, height) = (self.width(), self.height());
+
+        // The starting position itself always wraps around
+        let x = x % width;
+        let y = y % height;
+
+        for (i, bits) in rows.iter().enumerate() {
+            // Each entry in a sprite draws on one line.
+            let row = y + i;
+            if row >= height && clip {
+                break;
+            }
+            let offset = (row % height) * width;
+
+            // Loop through the bits in the current row and set the gfx
+            // values based on them, starting with the most significant bit.
+            for j in 0..row_width {
+                if (bits >> (15 - j)) & 0x01 == 0 {
+                    continue;
+                }
+                let pos: usize = x + j;
+                if pos >= width && clip {
+                    break;
+                }
+                let index = offset + pos % width;
+
+                // Check the previous state of the pixel and check if it
+                // is erased, if so then there was a sprite has_collided.
+                if self.gfx[index] & plane != 0 {
+                    has_collided = true;
+                }
+
+                // Draw the bit to the gfx.
+                self.gfx[index] ^= plane;
+            }
+        }
+
+        has_collided
+    }
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fmt;
+
+// Everything that can go wrong while the machine runs a ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    // The operand at pc does not decode to any known instruction
+    UnknownOpcode { pc: u16, opcode: u16 },
+    // A call was made while all stack entries were in use
+    StackOverflow { pc: u16 },
+    // A return was made while the stack was empty
+    StackUnderflow { pc: u16 },
+    // An access to an address outside of the RAM
+    MemoryOutOfBounds { addr: usize },
+    // The ROM does not fit in the RAM after the program start
+    RomTooLarge { size: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Chip8Error::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:#06X} at {:#05X}", opcode, pc)
+            }
+            Chip8Error::StackOverflow { pc } => write!(f, "stack overflow at {:#05X}", pc),
+            Chip8Error::StackUnderflow { pc } => write!(f, "stack underflow at {:#05X}", pc),
+            Chip8Error::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at {:#X}", addr)
+            }
+            Chip8Error::RomTooLarge { size } => {
+                write!(f, "ROM of {} bytes does not fit in memory", size)
+            }
+        }
+    }
+}
+
+impl Error for Chip8Error {}
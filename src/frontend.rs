@@ -0,0 +1,564 @@
+use crate::sound::SoundPlayer;
+use chip8::bindings::Bindings;
+use chip8::cpu::StepOutcome;
+use chip8::debugger::Debugger;
+use chip8::display;
+use chip8::error::Chip8Error;
+use chip8::gdb::GdbStub;
+use chip8::keymap::Keymap;
+use chip8::movie::{Movie, MoviePlayer};
+use chip8::rewind::Rewind;
+use chip8::Chip8;
+
+use ggez::event;
+use ggez::graphics::{self, Canvas, Color, DrawParam, Mesh, MeshBuilder, Rect, Text};
+use ggez::input::gamepad::gilrs::{Axis, Button};
+use ggez::input::gamepad::GamepadId;
+use ggez::input::keyboard::{KeyCode, KeyInput};
+use ggez::{Context, GameResult};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+// Save states go in numbered slots next to the ROM
+const SAVE_SLOTS: u8 = 10;
+const SAVE_KEY: KeyCode = KeyCode::F5;
+const LOAD_KEY: KeyCode = KeyCode::F9;
+const PREVIOUS_SLOT_KEY: KeyCode = KeyCode::F6;
+const NEXT_SLOT_KEY: KeyCode = KeyCode::F7;
+// Gameplay runs backwards while this is held
+const REWIND_KEY: KeyCode = KeyCode::Back;
+// Pauses the machine when the debugger is enabled
+const DEBUG_KEY: KeyCode = KeyCode::F12;
+// How far a stick has to be pushed to press a key
+const AXIS_THRESHOLD: f32 = 0.5;
+
+// An input movie that is being recorded to a file, or played back
+pub enum MovieMode {
+    Record { movie: Movie, path: PathBuf },
+    Play(MoviePlayer),
+}
+
+// How the keyboard and the gamepads reach the keypad: the bindings turn
+// them into keys of the host keypad, and the game's keymap turns those into
+// CHIP-8 keys
+pub struct Controls {
+    pub bindings: Bindings,
+    pub keymap: Keymap,
+}
+
+impl Controls {
+    // The host keypad key a key of the keyboard presses, if it is bound
+    fn key(&self, keycode: KeyCode) -> Option<u8> {
+        self.bindings.key(&format!("{:?}", keycode))
+    }
+
+    // The same for a button or stick direction of a player's gamepad
+    fn pad_key(&self, player: usize, control: &str) -> Option<u8> {
+        self.bindings.pad_key(player, control)
+    }
+}
+
+// The debugger and whatever drives it: command lines typed into the
+// terminal, which are read on their own thread so that the window keeps
+// running, and a gdb connection
+pub struct DebugSession {
+    debugger: Debugger,
+    terminal: Option<Receiver<String>>,
+    gdb: Option<GdbStub>,
+    // Whether the prompt needs to be printed again
+    prompt: bool,
+}
+
+impl DebugSession {
+    pub fn new(debugger: Debugger, terminal: bool, gdb: Option<GdbStub>) -> DebugSession {
+        let terminal = if terminal {
+            let (sender, lines) = mpsc::channel();
+            thread::spawn(move || {
+                for line in io::stdin().lock().lines().map_while(Result::ok) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            Some(lines)
+        } else {
+            None
+        };
+        DebugSession {
+            debugger,
+            prompt: terminal.is_some(),
+            terminal,
+            gdb,
+        }
+    }
+
+    // Run the commands that came in since the last update, stopping at
+    // the first one that runs the machine into an error
+    fn execute(&mut self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        if let Some(ref lines) = self.terminal {
+            while let Ok(line) = lines.try_recv() {
+                self.prompt = true;
+                self.debugger.execute(chip8, &line)?;
+            }
+        }
+        if let Some(ref mut gdb) = self.gdb {
+            gdb.poll(&mut self.debugger, chip8)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        let output = self.debugger.take_output();
+        if output.is_empty() && !self.prompt {
+            return;
+        }
+        print!("{}", output);
+        if self.terminal.is_some() && self.debugger.paused {
+            print!("(chip8) ");
+        }
+        let _ = io::stdout().flush();
+        self.prompt = false;
+    }
+}
+
+// The ggez window around the emulator core: it feeds the keyboard into the
+// keypad, renders the display and turns the buzzer into sound
+pub struct Frontend {
+    chip8: Chip8,
+    // Plays the buzzer, or the XO-CHIP audio pattern once a ROM loads one
+    sound: Option<SoundPlayer>,
+    // Set when the emulator hit an error, the machine is halted from then on
+    error: Option<Chip8Error>,
+    // The ROM that is running, its save states are stored next to it
+    rom_path: PathBuf,
+    // The save state slot that the save and load keys use
+    slot: u8,
+    // The history of the machine and whether it is being stepped back
+    rewind: Rewind,
+    rewinding: bool,
+    // Movies only replay exactly when nothing but the recorded keypad
+    // changes the machine, so rewinding and loading states are off
+    // while one is active
+    movie: Option<MovieMode>,
+    // Set by --debug and --gdb, the machine only runs while it is not paused
+    debug: Option<DebugSession>,
+    // The keys of the keyboard that are held, and which CHIP-8 keys they
+    // stand for
+    held: HashSet<KeyCode>,
+    controls: Controls,
+    // The gamepads in the order they were first used, the first one is
+    // player 1. The buttons and stick directions that are held on them go
+    // by player
+    pads: Vec<GamepadId>,
+    held_controls: HashSet<(usize, String)>,
+}
+
+impl Frontend {
+    pub fn new(
+        chip8: Chip8,
+        sound: Option<SoundPlayer>,
+        rom_path: &Path,
+        rewind: Rewind,
+        movie: Option<MovieMode>,
+        debug: Option<DebugSession>,
+        controls: Controls,
+    ) -> Frontend {
+        Frontend {
+            chip8,
+            sound,
+            error: None,
+            rom_path: rom_path.to_path_buf(),
+            slot: 1,
+            rewind,
+            rewinding: false,
+            movie,
+            debug,
+            held: HashSet::new(),
+            controls,
+            pads: Vec::new(),
+            held_controls: HashSet::new(),
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.debug
+            .as_ref()
+            .is_some_and(|session| session.debugger.paused)
+    }
+
+    // Stop the machine after an error, it can still be inspected in the
+    // debugger
+    fn halt(&mut self, e: Chip8Error) {
+        eprintln!("Emulator halted: {}", e);
+        eprintln!("{:?}", self.chip8.cpu);
+        self.flush_trace();
+        if let Some(ref mut session) = self.debug {
+            session.debugger.pause(&self.chip8);
+        }
+        self.error = Some(e);
+    }
+
+    // Bring the keypad in line with the keys and gamepad controls that are
+    // held, each key goes up only once nothing that presses it is held
+    // anymore
+    fn update_keypad(&mut self) {
+        if self.is_playing_movie() {
+            return;
+        }
+        let keys = self
+            .held
+            .iter()
+            .filter_map(|keycode| self.controls.key(*keycode));
+        let pads = self
+            .held_controls
+            .iter()
+            .filter_map(|(player, control)| self.controls.pad_key(*player, control));
+        let host_keys = keys.chain(pads).fold(0, |keys, key| keys | 1 << key);
+        self.chip8
+            .bus
+            .keypad
+            .set_state(self.controls.keymap.apply(host_keys));
+    }
+
+    // The trace file is complete up to the last instruction
+    fn flush_trace(&mut self) {
+        if let Some(ref mut trace) = self.chip8.trace {
+            if let Err(e) = trace.finish() {
+                eprintln!("Could not write the trace file: {}", e);
+            }
+        }
+    }
+
+    // The player a gamepad belongs to
+    fn player(&mut self, id: GamepadId) -> usize {
+        match self.pads.iter().position(|pad| *pad == id) {
+            Some(idx) => idx + 1,
+            None => {
+                self.pads.push(id);
+                println!("Gamepad joined as player {}", self.pads.len());
+                self.pads.len()
+            }
+        }
+    }
+
+    fn is_playing_movie(&self) -> bool {
+        matches!(self.movie, Some(MovieMode::Play(_)))
+    }
+
+    fn slot_path(&self) -> PathBuf {
+        self.rom_path.with_extension(format!("state{}", self.slot))
+    }
+
+    fn save_state(&self) {
+        let path = self.slot_path();
+        match fs::write(&path, self.chip8.save_state()) {
+            Ok(()) => println!("Saved state to {}", path.display()),
+            Err(e) => eprintln!("Could not save state to {}: {}", path.display(), e),
+        }
+    }
+
+    fn load_state(&mut self) {
+        if self.movie.is_some() {
+            eprintln!("Save states can not be loaded while a movie is active");
+            return;
+        }
+
+        let path = self.slot_path();
+        let result = fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| self.chip8.load_state(&data).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => {
+                // A state from before an error brings the machine back to
+                // life. The keys held now win over the ones in the state
+                self.error = None;
+                self.update_keypad();
+                println!("Loaded state from {}", path.display());
+            }
+            Err(e) => eprintln!("Could not load state from {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl event::EventHandler for Frontend {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        // Commands typed into the terminal run before the frames
+        if let Some(ref mut session) = self.debug {
+            let result = session.execute(&mut self.chip8);
+            if session.debugger.quit {
+                ctx.request_quit();
+                return Ok(());
+            }
+            if let Err(e) = result {
+                self.halt(e);
+            }
+        }
+
+        // Run as many 60 Hz frames as have passed since the last update,
+        // independent of how often ggez calls us
+        while ctx.time.check_update_time(chip8::FRAME_RATE) {
+            // A paused debugger holds the machine still
+            if self.is_paused() {
+                continue;
+            }
+
+            if self.rewinding && self.movie.is_none() {
+                // Going back to before an error brings the machine back to
+                // life. The keys held now win over the ones in the snapshot
+                match self.rewind.rewind(&mut self.chip8) {
+                    Ok(true) => {
+                        self.error = None;
+                        self.update_keypad();
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        eprintln!("Could not rewind: {}", e);
+                        self.rewinding = false;
+                    }
+                }
+                continue;
+            }
+
+            // Once halted the last frame stays on screen together with the error
+            if self.error.is_some() {
+                continue;
+            }
+
+            // The keypad comes from the movie while playing one back,
+            // and is written to it while recording. A frame that the
+            // debugger stopped in the middle of already has its keys
+            let frame_start = self
+                .debug
+                .as_ref()
+                .is_none_or(|session| session.debugger.at_frame_start());
+            match self.movie {
+                _ if !frame_start => {}
+                Some(MovieMode::Play(ref mut player)) => match player.next_frame() {
+                    Some((keys, repressed)) => {
+                        self.chip8.bus.keypad.set_state(keys);
+                        self.chip8.bus.keypad.repress(repressed);
+                    }
+                    None => {
+                        println!("Movie finished, the keyboard is back in control");
+                        self.movie = None;
+                        self.update_keypad();
+                    }
+                },
+                Some(MovieMode::Record { ref mut movie, .. }) => {
+                    let keypad = &self.chip8.bus.keypad;
+                    movie.record(keypad.state(), keypad.pressed())
+                }
+                None => {}
+            }
+
+            let result = match self.debug {
+                Some(ref mut session) => session.debugger.run_frame(&mut self.chip8),
+                None => self.chip8.run_frame(),
+            };
+            if self.is_paused() {
+                // Stopped at a breakpoint, the rest of the frame runs later
+                break;
+            }
+            self.rewind.capture(&self.chip8);
+            match result {
+                Ok(StepOutcome::Exited) => {
+                    // The ROM asked to stop the interpreter
+                    ctx.request_quit();
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.halt(e);
+                    break;
+                }
+            }
+        }
+
+        // Sound the buzzer for exactly as long as the sound timer is
+        // non-zero, and never once the machine is halted
+        if let Some(ref sound) = self.sound {
+            let playing = self.error.is_none() && !self.is_paused() && self.chip8.is_buzzer_on();
+            sound.update(self.chip8.cpu.audio_pattern, self.chip8.cpu.pitch, playing);
+        }
+
+        if let Some(ref mut session) = self.debug {
+            session.flush();
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut canvas = Canvas::from_frame(ctx, Color::from(PALETTE[0]));
+
+        // The window always has the same size, in high resolution
+        // the pixels are smaller
+        let screen = &self.chip8.bus.display;
+        let pixel_size = (display::PIXEL_SIZE * display::WIDTH / screen.width()) as f32;
+
+        // Collect all lit pixels into a single mesh
+        let mut mesh = MeshBuilder::new();
+        let mut lit = false;
+        for col in 0..screen.width() {
+            for row in 0..screen.height() {
+                let pixel = screen.get_pixel(col, row);
+                if pixel != 0 {
+                    let color = Color::from(PALETTE[pixel as usize]);
+                    let rect = Rect::new(
+                        col as f32 * pixel_size,
+                        row as f32 * pixel_size,
+                        pixel_size,
+                        pixel_size,
+                    );
+                    mesh.rectangle(graphics::DrawMode::fill(), rect, color)?;
+                    lit = true;
+                }
+            }
+        }
+        if lit {
+            let mesh = Mesh::from_data(ctx, mesh.build());
+            canvas.draw(&mesh, DrawParam::default());
+        }
+
+        if let Some(ref e) = self.error {
+            let text = Text::new(format!("Emulator halted: {}", e));
+            canvas.draw(
+                &text,
+                DrawParam::default().dest([10.0, 10.0]).color(Color::RED),
+            );
+        }
+
+        canvas.finish(ctx)?;
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> GameResult<bool> {
+        self.flush_trace();
+
+        // A recording is written out when the window closes
+        if let Some(MovieMode::Record {
+            ref movie,
+            ref path,
+        }) = self.movie
+        {
+            match fs::write(path, movie.to_string()) {
+                Ok(()) => println!(
+                    "Saved movie of {} frames to {}",
+                    movie.frames,
+                    path.display()
+                ),
+                Err(e) => eprintln!("Could not save movie to {}: {}", path.display(), e),
+            }
+        }
+        Ok(false)
+    }
+
+    fn key_down_event(&mut self, _ctx: &mut Context, input: KeyInput, repeat: bool) -> GameResult {
+        // Keys that are held down are already pressed
+        if repeat {
+            return Ok(());
+        }
+        match input.keycode {
+            Some(SAVE_KEY) => self.save_state(),
+            Some(LOAD_KEY) => self.load_state(),
+            Some(PREVIOUS_SLOT_KEY) => {
+                self.slot = (self.slot + SAVE_SLOTS - 1) % SAVE_SLOTS;
+                println!("Save state slot {}", self.slot);
+            }
+            Some(REWIND_KEY) => self.rewinding = true,
+            Some(DEBUG_KEY) if self.debug.is_some() => {
+                if let Some(ref mut session) = self.debug {
+                    session.debugger.pause(&self.chip8);
+                }
+            }
+            Some(NEXT_SLOT_KEY) => {
+                self.slot = (self.slot + 1) % SAVE_SLOTS;
+                println!("Save state slot {}", self.slot);
+            }
+            Some(keycode) => {
+                self.held.insert(keycode);
+                self.update_keypad();
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> GameResult {
+        if input.keycode == Some(REWIND_KEY) {
+            self.rewinding = false;
+            self.update_keypad();
+            println!("Rewind history: {:.1} seconds", self.rewind.seconds());
+            return Ok(());
+        }
+        if let Some(keycode) = input.keycode {
+            if self.held.remove(&keycode) {
+                self.update_keypad();
+            }
+        }
+        Ok(())
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) -> GameResult {
+        let player = self.player(id);
+        self.held_controls.insert((player, format!("{:?}", button)));
+        self.update_keypad();
+        Ok(())
+    }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: Button,
+        id: GamepadId,
+    ) -> GameResult {
+        let player = self.player(id);
+        if self
+            .held_controls
+            .remove(&(player, format!("{:?}", button)))
+        {
+            self.update_keypad();
+        }
+        Ok(())
+    }
+
+    // A stick pushed far enough one way holds the direction of its axis
+    // with that sign, LeftStickX- or LeftStickX+
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: Axis,
+        value: f32,
+        id: GamepadId,
+    ) -> GameResult {
+        let player = self.player(id);
+        let name = format!("{:?}", axis);
+        self.held_controls.retain(|(held_player, control)| {
+            *held_player != player || control.trim_end_matches(['+', '-']) != name
+        });
+        if value.abs() > AXIS_THRESHOLD {
+            let sign = if value > 0.0 { '+' } else { '-' };
+            self.held_controls
+                .insert((player, format!("{}{}", name, sign)));
+        }
+        self.update_keypad();
+        Ok(())
+    }
+}
+
+// The colors of the pixels, indexed by which of the two planes are set
+const PALETTE: [[f32; 4]; 4] = [
+    [0.0, 0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.3, 1.0],
+    [1.0, 0.3, 0.2, 1.0],
+    [1.0, 1.0, 0.8, 1.0],
+];
@@ -0,0 +1,454 @@
+use crate::debugger::{Breakpoint, Debugger};
+use crate::error::Chip8Error;
+use crate::Chip8;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+// A stub for the GDB remote serial protocol, so that gdb and other
+// debuggers that speak it can attach to the emulator over TCP:
+//
+//   $ cargo run -- --gdb 9000 games/PONG.ch8
+//   (gdb) target remote localhost:9000
+//
+// It drives the same Debugger as the terminal commands do, so breakpoints
+// and pausing work the same way. The socket never blocks, the frontend
+// polls the stub once per update. gdb has no CHIP-8 architecture, the
+// registers are described by the target description below instead: V0 to
+// VF, I, PC, SP, DT and ST, in little endian.
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.cpu">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="16" type="uint16"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+// The registers in the order of the target description
+const REGISTERS: usize = 21;
+const I: usize = 16;
+const PC: usize = 17;
+const SP: usize = 18;
+const DT: usize = 19;
+const ST: usize = 20;
+
+// Why the machine stopped, as the signal gdb knows it by
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+// The largest packet the stub accepts, as told to the client
+const PACKET_SIZE: usize = 0x1000;
+
+pub struct GdbStub {
+    listener: TcpListener,
+    // Only one debugger can be attached at a time
+    client: Option<TcpStream>,
+    // Received bytes that do not make up a whole packet yet
+    buffer: Vec<u8>,
+    // The client waits for a stop reply while the machine runs
+    running: bool,
+}
+
+impl GdbStub {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> io::Result<GdbStub> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbStub {
+            listener,
+            client: None,
+            buffer: Vec::new(),
+            running: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Handle everything the client sent since the last call, and tell it
+    // once the machine has stopped. Errors of the machine while stepping
+    // are passed on so that the frontend halts like it always does
+    pub fn poll(&mut self, debugger: &mut Debugger, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        if self.client.is_none() {
+            self.accept();
+        }
+
+        let connected = self.client.is_some();
+        let mut result = Ok(());
+        if self.receive() {
+            result = self.process(debugger, chip8);
+        }
+        if connected && self.client.is_none() {
+            // Nobody is left to continue a paused machine
+            debugger.resume();
+            return result;
+        }
+        if self.running && debugger.paused {
+            self.running = false;
+            self.send(&stop_reply(SIGTRAP));
+        }
+        result
+    }
+
+    fn accept(&mut self) {
+        match self.listener.accept() {
+            Ok((stream, _)) => {
+                if stream.set_nonblocking(true).is_ok() {
+                    let _ = stream.set_nodelay(true);
+                    self.client = Some(stream);
+                    self.buffer.clear();
+                    self.running = false;
+                }
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => eprintln!("Could not accept a gdb connection: {}", e),
+        }
+    }
+
+    // Read whatever has arrived, false once the client is gone
+    fn receive(&mut self) -> bool {
+        let client = match self.client {
+            Some(ref mut client) => client,
+            None => return false,
+        };
+
+        let mut bytes = [0; 1024];
+        loop {
+            match client.read(&mut bytes) {
+                Ok(0) => {
+                    self.client = None;
+                    return false;
+                }
+                Ok(count) => self.buffer.extend_from_slice(&bytes[..count]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => return true,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    self.client = None;
+                    return false;
+                }
+            }
+        }
+    }
+
+    // Split the received bytes into packets and answer them. Packets look
+    // like $data#checksum, a lone 0x03 interrupts the running machine
+    fn process(&mut self, debugger: &mut Debugger, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        while let Some(&first) = self.buffer.first() {
+            match first {
+                b'$' => {
+                    let end = match self.buffer.iter().position(|&b| b == b'#') {
+                        Some(end) if self.buffer.len() >= end + 3 => end,
+                        // Wait for the rest, unless it will never fit
+                        _ if self.buffer.len() > 2 * PACKET_SIZE => {
+                            self.buffer.clear();
+                            break;
+                        }
+                        _ => break,
+                    };
+                    let packet: Vec<u8> = self.buffer.drain(..end + 3).collect();
+                    let data = &packet[1..end];
+                    let checksum = String::from_utf8_lossy(&packet[end + 1..]);
+                    if u8::from_str_radix(&checksum, 16).ok() != Some(checksum_of(data)) {
+                        self.write(b"-");
+                        continue;
+                    }
+                    self.write(b"+");
+                    let data = String::from_utf8_lossy(data);
+                    match self.handle(&data, debugger, chip8) {
+                        Ok(Some(reply)) => self.send(&reply),
+                        Ok(None) => {}
+                        Err(e) => {
+                            self.send(&stop_reply(SIGTRAP));
+                            return Err(e);
+                        }
+                    }
+                }
+                0x03 => {
+                    self.buffer.remove(0);
+                    debugger.pause(chip8);
+                    if self.running {
+                        self.running = false;
+                        self.send(&stop_reply(SIGINT));
+                    }
+                }
+                // Acknowledgements, and anything outside of a packet
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The reply to a packet, None for packets that are answered later
+    fn handle(
+        &mut self,
+        packet: &str,
+        debugger: &mut Debugger,
+        chip8: &mut Chip8,
+    ) -> Result<Option<String>, Chip8Error> {
+        let error = || Some("E01".to_string());
+        let command = packet.chars().next().unwrap_or(' ');
+        let args = packet.get(1..).unwrap_or("");
+
+        let reply = match command {
+            '?' => Some(stop_reply(SIGTRAP)),
+            'g' => Some(
+                (0..REGISTERS)
+                    .map(|register| hex(&read_register(chip8, register)))
+                    .collect(),
+            ),
+            'G' => {
+                let mut bytes = match unhex(args) {
+                    Some(bytes) => bytes,
+                    None => return Ok(error()),
+                };
+                let mut result = Some("OK".to_string());
+                for register in 0..REGISTERS {
+                    let size = register_size(register);
+                    if bytes.len() < size {
+                        break;
+                    }
+                    let value: Vec<u8> = bytes.drain(..size).collect();
+                    if !write_register(chip8, register, &value) {
+                        result = error();
+                    }
+                }
+                result
+            }
+            'p' => match usize::from_str_radix(args, 16) {
+                Ok(register) if register < REGISTERS => Some(hex(&read_register(chip8, register))),
+                _ => error(),
+            },
+            'P' => {
+                let parsed = args.split_once('=').and_then(|(register, value)| {
+                    Some((usize::from_str_radix(register, 16).ok()?, unhex(value)?))
+                });
+                match parsed {
+                    Some((register, value)) if write_register(chip8, register, &value) => {
+                        Some("OK".to_string())
+                    }
+                    _ => error(),
+                }
+            }
+            'm' => {
+                let bytes = address_and_length(args).and_then(|(address, length)| {
+                    chip8
+                        .bus
+                        .ram
+                        .memory
+                        .get(address..address.checked_add(length)?)
+                });
+                match bytes {
+                    Some(bytes) => Some(hex(bytes)),
+                    None => error(),
+                }
+            }
+            'M' => {
+                let parsed = args.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = address_and_length(range)?;
+                    let data = unhex(data).filter(|data| data.len() == length)?;
+                    Some((address..address.checked_add(length)?, data))
+                });
+                match parsed {
+                    Some((range, data)) if range.end <= chip8.bus.ram.memory.len() => {
+                        chip8.bus.ram.memory[range].copy_from_slice(&data);
+                        Some("OK".to_string())
+                    }
+                    _ => error(),
+                }
+            }
+            // Software and hardware breakpoints are the same thing here
+            'Z' | 'z' => match breakpoint(args) {
+                Some(breakpoint) if command == 'Z' => {
+                    debugger.add_breakpoint(breakpoint);
+                    Some("OK".to_string())
+                }
+                Some(breakpoint) => {
+                    debugger.remove_breakpoint(breakpoint);
+                    Some("OK".to_string())
+                }
+                None => Some(String::new()),
+            },
+            's' => {
+                if let Ok(address) = u16::from_str_radix(args, 16) {
+                    chip8.cpu.set_pc(address);
+                }
+                debugger.pause(chip8);
+                debugger.step(chip8)?;
+                Some(stop_reply(SIGTRAP))
+            }
+            'c' => {
+                if let Ok(address) = u16::from_str_radix(args, 16) {
+                    chip8.cpu.set_pc(address);
+                }
+                debugger.resume();
+                self.running = true;
+                None
+            }
+            'q' => self.query(args),
+            // There is only one thread
+            'H' => Some("OK".to_string()),
+            'T' => Some("OK".to_string()),
+            'D' => {
+                // The machine runs on without the debugger
+                debugger.resume();
+                self.send("OK");
+                self.client = None;
+                None
+            }
+            'k' => {
+                debugger.quit = true;
+                self.client = None;
+                None
+            }
+            // An empty reply tells the client a packet is not supported
+            _ => Some(String::new()),
+        };
+        Ok(reply)
+    }
+
+    fn query(&self, query: &str) -> Option<String> {
+        let reply = if query.starts_with("Supported") {
+            format!("PacketSize={:x};qXfer:features:read+", PACKET_SIZE)
+        } else if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            // The description is sent in pieces of the size asked for,
+            // the last one starts with l instead of m
+            match address_and_length(range) {
+                Some((offset, length)) => {
+                    let rest = TARGET_XML.get(offset.min(TARGET_XML.len())..).unwrap_or("");
+                    if rest.len() <= length {
+                        format!("l{}", rest)
+                    } else {
+                        format!("m{}", &rest[..length])
+                    }
+                }
+                None => "E01".to_string(),
+            }
+        } else if query == "Attached" {
+            "1".to_string()
+        } else if query == "C" {
+            "QC1".to_string()
+        } else if query == "fThreadInfo" {
+            "m1".to_string()
+        } else if query == "sThreadInfo" {
+            "l".to_string()
+        } else {
+            String::new()
+        };
+        Some(reply)
+    }
+
+    fn send(&mut self, data: &str) {
+        let packet = format!("${}#{:02x}", data, checksum_of(data.as_bytes()));
+        self.write(packet.as_bytes());
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        if let Some(ref mut client) = self.client {
+            if client.write_all(bytes).is_err() {
+                self.client = None;
+            }
+        }
+    }
+}
+
+fn stop_reply(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, b| sum.wrapping_add(*b))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// None for anything but pairs of hex digits
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    (0..text.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(text.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+// ADDR,LENGTH as used by the memory and transfer packets
+fn address_and_length(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((
+        usize::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+// TYPE,ADDR,KIND of the breakpoint packets, only types 0 (software) and
+// 1 (hardware) stop on an address
+fn breakpoint(text: &str) -> Option<Breakpoint> {
+    let mut parts = text.split(',');
+    let kind = parts.next()?;
+    let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+    match kind {
+        "0" | "1" => Some(Breakpoint::Address(address)),
+        _ => None,
+    }
+}
+
+fn register_size(register: usize) -> usize {
+    match register {
+        I | PC | SP => 2,
+        _ => 1,
+    }
+}
+
+fn read_register(chip8: &Chip8, register: usize) -> Vec<u8> {
+    let cpu = &chip8.cpu;
+    match register {
+        I => cpu.i.to_le_bytes().to_vec(),
+        PC => cpu.pc.to_le_bytes().to_vec(),
+        SP => cpu.sp.to_le_bytes().to_vec(),
+        DT => vec![cpu.dt],
+        ST => vec![cpu.st],
+        _ => vec![cpu.gp[register]],
+    }
+}
+
+// False for unknown registers, values of the wrong size and stack
+// pointers past the end of the stack
+fn write_register(chip8: &mut Chip8, register: usize, value: &[u8]) -> bool {
+    if register >= REGISTERS || value.len() != register_size(register) {
+        return false;
+    }
+    let cpu = &mut chip8.cpu;
+    let word = || u16::from_le_bytes([value[0], value[1]]);
+    match register {
+        I => cpu.i = word(),
+        PC => cpu.set_pc(word()),
+        SP if word() as usize > cpu.stack.len() => return false,
+        SP => cpu.sp = word(),
+        DT => cpu.dt = value[0],
+        ST => cpu.st = value[0],
+        _ => cpu.gp[register] = value[0],
+    }
+    true
+}
@@ -0,0 +1,227 @@
+use crate::cpu::StepOutcome;
+use crate::debugger;
+use crate::error::Chip8Error;
+use crate::movie::MoviePlayer;
+use crate::quirks::Quirks;
+use crate::trace::Trace;
+use crate::Chip8;
+
+use std::ops::RangeInclusive;
+
+// The command line and the output of chip8-headless, which runs a ROM
+// without a window and reports the registers and the screen once it stops
+
+// Ten seconds of emulated time unless told otherwise
+pub const DEFAULT_FRAMES: u64 = 600;
+
+// Exit codes, so that scripts can tell a broken ROM from a bad command line
+pub const EXIT_USAGE: i32 = 1;
+pub const EXIT_EMULATOR_ERROR: i32 = 2;
+
+// A key that is held down for a number of frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Press {
+    pub frame: u64,
+    pub key: u8,
+    pub frames: u64,
+}
+
+impl Press {
+    // Parses FRAME:KEY or FRAME:KEY:FRAMES, the key is a hex digit and is
+    // held for a single frame unless given
+    pub fn parse(value: &str) -> Result<Press, String> {
+        let invalid = || format!("invalid key press '{}', expected FRAME:KEY[:FRAMES]", value);
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err(invalid());
+        }
+
+        let frame = parts[0].parse().map_err(|_| invalid())?;
+        let key = u8::from_str_radix(parts[1], 16).map_err(|_| invalid())?;
+        if key > 0xF {
+            return Err(invalid());
+        }
+        let frames = match parts.get(2) {
+            Some(frames) => frames.parse().map_err(|_| invalid())?,
+            None => 1,
+        };
+        Ok(Press { frame, key, frames })
+    }
+
+    pub fn is_down(&self, frame: u64) -> bool {
+        frame >= self.frame && frame < self.frame.saturating_add(self.frames)
+    }
+}
+
+pub struct Options {
+    pub rom: String,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    pub seed: u64,
+    pub frames: u64,
+    pub instructions: Option<u64>,
+    pub presses: Vec<Press>,
+    pub movie: Option<String>,
+    pub pbm: Option<String>,
+    pub ascii: bool,
+    pub trace: Option<String>,
+    pub trace_range: Option<RangeInclusive<u16>>,
+    pub trace_count: Option<u64>,
+}
+
+// Parses the command line, the first argument is the program name
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut rom = None;
+    let mut quirks = Quirks::default();
+    let mut instructions_per_frame = crate::DEFAULT_INSTRUCTIONS_PER_FRAME;
+    // Headless runs are reproducible by default
+    let mut seed = 0;
+    let mut frames = DEFAULT_FRAMES;
+    let mut instructions = None;
+    let mut presses = Vec::new();
+    let mut movie = None;
+    let mut pbm = None;
+    let mut ascii = false;
+    let mut trace = None;
+    let mut trace_range = None;
+    let mut trace_count = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let value = iter.next().ok_or("--quirks needs a preset")?;
+                quirks = value.parse()?;
+            }
+            "--ipf" => {
+                let value = iter.next().ok_or("--ipf needs a number")?;
+                instructions_per_frame = value
+                    .parse()
+                    .map_err(|_| format!("invalid instructions per frame '{}'", value))?;
+            }
+            "--seed" => {
+                let value = iter.next().ok_or("--seed needs a number")?;
+                seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed '{}'", value))?;
+            }
+            "--frames" => {
+                let value = iter.next().ok_or("--frames needs a number")?;
+                frames = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of frames '{}'", value))?;
+            }
+            "--instructions" => {
+                let value = iter.next().ok_or("--instructions needs a number")?;
+                instructions = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid number of instructions '{}'", value))?,
+                );
+            }
+            "--press" => {
+                let value = iter.next().ok_or("--press needs FRAME:KEY[:FRAMES]")?;
+                presses.push(Press::parse(value)?);
+            }
+            "--play" => {
+                movie = Some(iter.next().ok_or("--play needs a file")?.clone());
+            }
+            "--pbm" => {
+                pbm = Some(iter.next().ok_or("--pbm needs a file")?.clone());
+            }
+            "--ascii" => ascii = true,
+            "--trace" => {
+                trace = Some(iter.next().ok_or("--trace needs a file")?.clone());
+            }
+            "--trace-range" => {
+                let value = iter.next().ok_or("--trace-range needs START-END")?;
+                trace_range = Some(Trace::parse_range(value)?);
+            }
+            "--trace-count" => {
+                let value = iter.next().ok_or("--trace-count needs a number")?;
+                trace_count = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid number of instructions '{}'", value))?,
+                );
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(Options {
+        rom: rom.ok_or("no ROM file given")?,
+        quirks,
+        instructions_per_frame,
+        seed,
+        frames,
+        instructions,
+        presses,
+        movie,
+        // The screen goes to stdout when it is not written anywhere else
+        ascii: ascii || pbm.is_none(),
+        pbm,
+        trace,
+        trace_range,
+        trace_count,
+    })
+}
+
+// Run frame by frame like the window does until the frames run out, the
+// instruction limit is reached, the ROM exits or the machine hits an error.
+// Returns the number of frames that ran to the end
+pub fn run(
+    chip8: &mut Chip8,
+    options: &Options,
+    mut player: Option<&mut MoviePlayer>,
+) -> (u64, Result<(), Chip8Error>) {
+    chip8.instruction_limit = options.instructions;
+    let mut frame = 0;
+    while frame < options.frames && !chip8.reached_instruction_limit() {
+        let mut keys = pressed_keys(&options.presses, frame);
+        let mut repressed = 0;
+        if let Some(ref mut player) = player {
+            let (movie_keys, movie_repressed) = player.next_frame().unwrap_or((0, 0));
+            keys |= movie_keys;
+            repressed = movie_repressed;
+        }
+        chip8.bus.keypad.set_state(keys);
+        chip8.bus.keypad.repress(repressed);
+
+        match chip8.run_frame() {
+            Ok(outcome) => {
+                frame += 1;
+                if outcome == StepOutcome::Exited {
+                    break;
+                }
+            }
+            Err(e) => return (frame, Err(e)),
+        }
+    }
+    (frame, Ok(()))
+}
+
+// The keypad state for a frame, as a bitmask like Keypad::state
+pub fn pressed_keys(presses: &[Press], frame: u64) -> u16 {
+    presses
+        .iter()
+        .filter(|press| press.is_down(frame))
+        .fold(0, |keys, press| keys | (1 << press.key))
+}
+
+// Everything a failing test needs to see about the cpu, and the screen as
+// text if asked for
+pub fn report(chip8: &Chip8, frames: u64, ascii: bool) -> String {
+    let mut text = format!(
+        "frames: {} instructions: {}\n{}",
+        frames,
+        chip8.instructions,
+        debugger::registers(chip8)
+    );
+    if ascii {
+        text.push_str(&chip8.bus.display.to_ascii());
+    }
+    text
+}
@@ -0,0 +1,275 @@
+use std::fmt;
+
+// The instruction set of CHIP-8, SUPER-CHIP and XO-CHIP, shared by the cpu,
+// the disassembler and the assembler. The variants follow the CHIPPER
+// mnemonics, with the operands that tell the forms of LD, ADD, SE and SNE
+// apart in the name. x and y are register numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    // 00E0 and 00EE
+    Cls,
+    Ret,
+    // 00CN and 00DN, scroll down and up n lines
+    Scd(u8),
+    Scu(u8),
+    // 00FB to 00FF
+    Scr,
+    Scl,
+    Exit,
+    Low,
+    High,
+    // 1NNN and 2NNN
+    Jp(u16),
+    Call(u16),
+    // 3XKK, 4XKK, 5XY0 and 9XY0
+    SeByte { x: u8, kk: u8 },
+    SneByte { x: u8, kk: u8 },
+    SeReg { x: u8, y: u8 },
+    SneReg { x: u8, y: u8 },
+    // 5XY2 and 5XY3, Vx through Vy to and from memory at I
+    Save { x: u8, y: u8 },
+    Load { x: u8, y: u8 },
+    // 6XKK and 7XKK
+    LdByte { x: u8, kk: u8 },
+    AddByte { x: u8, kk: u8 },
+    // 8XY0 to 8XYE
+    LdReg { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    AddReg { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    Shr { x: u8, y: u8 },
+    Subn { x: u8, y: u8 },
+    Shl { x: u8, y: u8 },
+    // ANNN, BNNN, CXKK and DXYN
+    LdI(u16),
+    JpV0(u16),
+    Rnd { x: u8, kk: u8 },
+    Drw { x: u8, y: u8, n: u8 },
+    // EX9E and EXA1
+    Skp(u8),
+    Sknp(u8),
+    // F000, the 16 bit address to load into I is the next word
+    LdLong,
+    // FN01 and F002
+    Plane(u8),
+    Audio,
+    // FX07 to FX85
+    LdVxDt(u8),
+    LdVxK(u8),
+    LdDtVx(u8),
+    LdStVx(u8),
+    AddI(u8),
+    LdF(u8),
+    LdHf(u8),
+    LdB(u8),
+    Pitch(u8),
+    LdIVx(u8),
+    LdVxI(u8),
+    LdRVx(u8),
+    LdVxR(u8),
+}
+
+use Instruction::*;
+
+impl Instruction {
+    // None for words that are not an instruction
+    pub fn decode(opcode: u16) -> Option<Instruction> {
+        let nnn = opcode & 0x0FFF;
+        let kk = (opcode & 0x00FF) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+
+        let instruction = match (opcode >> 12, x, y, n) {
+            (0x0, 0x0, 0xC, _) => Scd(n),
+            (0x0, 0x0, 0xD, _) => Scu(n),
+            (0x0, 0x0, 0xE, 0x0) => Cls,
+            (0x0, 0x0, 0xE, 0xE) => Ret,
+            (0x0, 0x0, 0xF, 0xB) => Scr,
+            (0x0, 0x0, 0xF, 0xC) => Scl,
+            (0x0, 0x0, 0xF, 0xD) => Exit,
+            (0x0, 0x0, 0xF, 0xE) => Low,
+            (0x0, 0x0, 0xF, 0xF) => High,
+            (0x1, _, _, _) => Jp(nnn),
+            (0x2, _, _, _) => Call(nnn),
+            (0x3, _, _, _) => SeByte { x, kk },
+            (0x4, _, _, _) => SneByte { x, kk },
+            (0x5, _, _, 0x0) => SeReg { x, y },
+            (0x5, _, _, 0x2) => Save { x, y },
+            (0x5, _, _, 0x3) => Load { x, y },
+            (0x6, _, _, _) => LdByte { x, kk },
+            (0x7, _, _, _) => AddByte { x, kk },
+            (0x8, _, _, 0x0) => LdReg { x, y },
+            (0x8, _, _, 0x1) => Or { x, y },
+            (0x8, _, _, 0x2) => And { x, y },
+            (0x8, _, _, 0x3) => Xor { x, y },
+            (0x8, _, _, 0x4) => AddReg { x, y },
+            (0x8, _, _, 0x5) => Sub { x, y },
+            (0x8, _, _, 0x6) => Shr { x, y },
+            (0x8, _, _, 0x7) => Subn { x, y },
+            (0x8, _, _, 0xE) => Shl { x, y },
+            (0x9, _, _, 0x0) => SneReg { x, y },
+            (0xA, _, _, _) => LdI(nnn),
+            (0xB, _, _, _) => JpV0(nnn),
+            (0xC, _, _, _) => Rnd { x, kk },
+            (0xD, _, _, _) => Drw { x, y, n },
+            (0xE, _, 0x9, 0xE) => Skp(x),
+            (0xE, _, 0xA, 0x1) => Sknp(x),
+            (0xF, 0x0, 0x0, 0x0) => LdLong,
+            (0xF, _, 0x0, 0x1) => Plane(x),
+            (0xF, 0x0, 0x0, 0x2) => Audio,
+            (0xF, _, 0x0, 0x7) => LdVxDt(x),
+            (0xF, _, 0x0, 0xA) => LdVxK(x),
+            (0xF, _, 0x1, 0x5) => LdDtVx(x),
+            (0xF, _, 0x1, 0x8) => LdStVx(x),
+            (0xF, _, 0x1, 0xE) => AddI(x),
+            (0xF, _, 0x2, 0x9) => LdF(x),
+            (0xF, _, 0x3, 0x0) => LdHf(x),
+            (0xF, _, 0x3, 0x3) => LdB(x),
+            (0xF, _, 0x3, 0xA) => Pitch(x),
+            (0xF, _, 0x5, 0x5) => LdIVx(x),
+            (0xF, _, 0x6, 0x5) => LdVxI(x),
+            (0xF, _, 0x7, 0x5) => LdRVx(x),
+            (0xF, _, 0x8, 0x5) => LdVxR(x),
+            _ => return None,
+        };
+        Some(instruction)
+    }
+
+    // Out of range operands are masked to the bits the opcode has for them
+    pub fn encode(&self) -> u16 {
+        let xy = |base: u16, x: u8, y: u8| base | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4;
+        let xkk = |base: u16, x: u8, kk: u8| base | (x as u16 & 0xF) << 8 | kk as u16;
+        let nnn = |base: u16, nnn: u16| base | (nnn & 0xFFF);
+
+        match *self {
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Scd(n) => 0x00C0 | (n as u16 & 0xF),
+            Scu(n) => 0x00D0 | (n as u16 & 0xF),
+            Scr => 0x00FB,
+            Scl => 0x00FC,
+            Exit => 0x00FD,
+            Low => 0x00FE,
+            High => 0x00FF,
+            Jp(addr) => nnn(0x1000, addr),
+            Call(addr) => nnn(0x2000, addr),
+            SeByte { x, kk } => xkk(0x3000, x, kk),
+            SneByte { x, kk } => xkk(0x4000, x, kk),
+            SeReg { x, y } => xy(0x5000, x, y),
+            Save { x, y } => xy(0x5002, x, y),
+            Load { x, y } => xy(0x5003, x, y),
+            LdByte { x, kk } => xkk(0x6000, x, kk),
+            AddByte { x, kk } => xkk(0x7000, x, kk),
+            LdReg { x, y } => xy(0x8000, x, y),
+            Or { x, y } => xy(0x8001, x, y),
+            And { x, y } => xy(0x8002, x, y),
+            Xor { x, y } => xy(0x8003, x, y),
+            AddReg { x, y } => xy(0x8004, x, y),
+            Sub { x, y } => xy(0x8005, x, y),
+            Shr { x, y } => xy(0x8006, x, y),
+            Subn { x, y } => xy(0x8007, x, y),
+            Shl { x, y } => xy(0x800E, x, y),
+            SneReg { x, y } => xy(0x9000, x, y),
+            LdI(addr) => nnn(0xA000, addr),
+            JpV0(addr) => nnn(0xB000, addr),
+            Rnd { x, kk } => xkk(0xC000, x, kk),
+            Drw { x, y, n } => xy(0xD000, x, y) | (n as u16 & 0xF),
+            Skp(x) => xy(0xE09E, x, 0),
+            Sknp(x) => xy(0xE0A1, x, 0),
+            LdLong => 0xF000,
+            Plane(n) => xy(0xF001, n, 0),
+            Audio => 0xF002,
+            LdVxDt(x) => xy(0xF007, x, 0),
+            LdVxK(x) => xy(0xF00A, x, 0),
+            LdDtVx(x) => xy(0xF015, x, 0),
+            LdStVx(x) => xy(0xF018, x, 0),
+            AddI(x) => xy(0xF01E, x, 0),
+            LdF(x) => xy(0xF029, x, 0),
+            LdHf(x) => xy(0xF030, x, 0),
+            LdB(x) => xy(0xF033, x, 0),
+            Pitch(x) => xy(0xF03A, x, 0),
+            LdIVx(x) => xy(0xF055, x, 0),
+            LdVxI(x) => xy(0xF065, x, 0),
+            LdRVx(x) => xy(0xF075, x, 0),
+            LdVxR(x) => xy(0xF085, x, 0),
+        }
+    }
+
+    // The length in bytes, F000 NNNN is the only one with an extra word
+    pub fn size(&self) -> u16 {
+        if *self == LdLong {
+            4
+        } else {
+            2
+        }
+    }
+
+    // The CHIPPER text of the instruction, with label turning addresses
+    // into the names to print for them
+    pub fn format(&self, label: &dyn Fn(u16) -> String) -> String {
+        match *self {
+            Cls => "CLS".to_string(),
+            Ret => "RET".to_string(),
+            Scd(n) => format!("SCD   {}", n),
+            Scu(n) => format!("SCU   {}", n),
+            Scr => "SCR".to_string(),
+            Scl => "SCL".to_string(),
+            Exit => "EXIT".to_string(),
+            Low => "LOW".to_string(),
+            High => "HIGH".to_string(),
+            Jp(addr) => format!("JP    {}", label(addr)),
+            Call(addr) => format!("CALL  {}", label(addr)),
+            SeByte { x, kk } => format!("SE    V{:X}, #{:02X}", x, kk),
+            SneByte { x, kk } => format!("SNE   V{:X}, #{:02X}", x, kk),
+            SeReg { x, y } => format!("SE    V{:X}, V{:X}", x, y),
+            SneReg { x, y } => format!("SNE   V{:X}, V{:X}", x, y),
+            Save { x, y } => format!("SAVE  V{:X}, V{:X}", x, y),
+            Load { x, y } => format!("LOAD  V{:X}, V{:X}", x, y),
+            LdByte { x, kk } => format!("LD    V{:X}, #{:02X}", x, kk),
+            AddByte { x, kk } => format!("ADD   V{:X}, #{:02X}", x, kk),
+            LdReg { x, y } => format!("LD    V{:X}, V{:X}", x, y),
+            Or { x, y } => format!("OR    V{:X}, V{:X}", x, y),
+            And { x, y } => format!("AND   V{:X}, V{:X}", x, y),
+            Xor { x, y } => format!("XOR   V{:X}, V{:X}", x, y),
+            AddReg { x, y } => format!("ADD   V{:X}, V{:X}", x, y),
+            Sub { x, y } => format!("SUB   V{:X}, V{:X}", x, y),
+            // The single register form leaves y at zero
+            Shr { x, y: 0 } => format!("SHR   V{:X}", x),
+            Shr { x, y } => format!("SHR   V{:X}, V{:X}", x, y),
+            Subn { x, y } => format!("SUBN  V{:X}, V{:X}", x, y),
+            Shl { x, y: 0 } => format!("SHL   V{:X}", x),
+            Shl { x, y } => format!("SHL   V{:X}, V{:X}", x, y),
+            LdI(addr) => format!("LD    I, {}", label(addr)),
+            JpV0(addr) => format!("JP    V0, {}", label(addr)),
+            Rnd { x, kk } => format!("RND   V{:X}, #{:02X}", x, kk),
+            Drw { x, y, n } => format!("DRW   V{:X}, V{:X}, {}", x, y, n),
+            Skp(x) => format!("SKP   V{:X}", x),
+            Sknp(x) => format!("SKNP  V{:X}", x),
+            LdLong => "LD    I, LONG".to_string(),
+            Plane(n) => format!("PLANE {}", n),
+            Audio => "AUDIO".to_string(),
+            LdVxDt(x) => format!("LD    V{:X}, DT", x),
+            LdVxK(x) => format!("LD    V{:X}, K", x),
+            LdDtVx(x) => format!("LD    DT, V{:X}", x),
+            LdStVx(x) => format!("LD    ST, V{:X}", x),
+            AddI(x) => format!("ADD   I, V{:X}", x),
+            LdF(x) => format!("LD    F, V{:X}", x),
+            LdHf(x) => format!("LD    HF, V{:X}", x),
+            LdB(x) => format!("LD    B, V{:X}", x),
+            Pitch(x) => format!("PITCH V{:X}", x),
+            LdIVx(x) => format!("LD    [I], V{:X}", x),
+            LdVxI(x) => format!("LD    V{:X}, [I]", x),
+            LdRVx(x) => format!("LD    R, V{:X}", x),
+            LdVxR(x) => format!("LD    V{:X}, R", x),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(&|addr| format!("#{:03X}", addr)))
+    }
+}
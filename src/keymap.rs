@@ -0,0 +1,70 @@
+use std::fmt;
+use std::str::FromStr;
+
+// A per-game remapping of the keypad, in the format of the .c8k files that
+// come with some of the games: 16 hex digits, where digit n is the key on
+// the host keypad that presses CHIP-8 key n. BLINKY steers with keys 3, 6,
+// 7 and 8, its keymap puts them under 2, 8, 4 and 6:
+//
+//   0122458469ABCDE5
+//
+// One host key can press several CHIP-8 keys, and keys that no digit names
+// press nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keymap {
+    keys: [u8; 16],
+}
+
+impl Keymap {
+    // Every key presses itself
+    pub fn new() -> Keymap {
+        let mut keys = [0; 16];
+        for (idx, key) in keys.iter_mut().enumerate() {
+            *key = idx as u8;
+        }
+        Keymap { keys }
+    }
+
+    // The CHIP-8 keys that are down while the given host keys are, both as
+    // bitmasks like Keypad::state
+    pub fn apply(&self, host: u16) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| host & (1 << **key) != 0)
+            .fold(0, |state, (idx, _)| state | (1 << idx))
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new()
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Keymap, String> {
+        let text = text.trim();
+        let invalid = || format!("invalid keymap '{}', expected 16 hex digits", text);
+        if text.chars().count() != 16 {
+            return Err(invalid());
+        }
+
+        let mut keys = [0; 16];
+        for (key, c) in keys.iter_mut().zip(text.chars()) {
+            *key = c.to_digit(16).ok_or_else(invalid)? as u8;
+        }
+        Ok(Keymap { keys })
+    }
+}
+
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for key in self.keys.iter() {
+            write!(f, "{:X}", key)?;
+        }
+        Ok(())
+    }
+}
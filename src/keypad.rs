@@ -0,0 +1,131 @@
+pub struct Keypad {
+    // There are 16 different keys on the keypad
+    pub keypad: [bool; 16],
+    // The keys that went down and up since the frame started, as bitmasks
+    // like state
+    pressed: u16,
+    released: u16,
+    // Keys that were let go of in the same frame they were pressed in. They
+    // stay down until the frame ends, so a quick tap is never missed
+    releasing: u16,
+}
+
+impl Keypad {
+    pub fn new() -> Keypad {
+        Keypad {
+            keypad: [false; 16],
+            pressed: 0,
+            released: 0,
+            releasing: 0,
+        }
+    }
+
+    // Pressing a key that is already down, like the host does when it
+    // repeats a held key, changes nothing
+    pub fn press_key(&mut self, key: u8) {
+        let bit = 1 << key;
+        self.releasing &= !bit;
+        if !self.keypad[key as usize] {
+            self.keypad[key as usize] = true;
+            self.pressed |= bit;
+        }
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        let bit = 1 << key;
+        if !self.keypad[key as usize] {
+            return;
+        }
+        if self.pressed & bit != 0 {
+            self.releasing |= bit;
+        } else {
+            self.keypad[key as usize] = false;
+            self.released |= bit;
+        }
+    }
+
+    pub fn is_key_down(&self, key: usize) -> bool {
+        self.keypad[key]
+    }
+
+    // The keys that went down since the frame started
+    pub fn pressed(&self) -> u16 {
+        self.pressed
+    }
+
+    // The keys that went up since the frame started
+    pub fn released(&self) -> u16 {
+        self.released
+    }
+
+    // The lowest key that went down since the frame started, it is only
+    // handed out once
+    pub fn take_press(&mut self) -> Option<u8> {
+        if self.pressed == 0 {
+            return None;
+        }
+        let key = self.pressed.trailing_zeros() as u8;
+        self.pressed &= !(1 << key);
+        Some(key)
+    }
+
+    // Forget the edges of the frame that ended, and let go of the keys that
+    // were tapped during it
+    pub fn next_frame(&mut self) {
+        self.pressed = 0;
+        self.released = 0;
+        let releasing = std::mem::take(&mut self.releasing);
+        for key in 0..16 {
+            if releasing & (1 << key) != 0 {
+                self.release_key(key);
+            }
+        }
+    }
+
+    // All keys as a bitmask, bit n is set while key n is down
+    pub fn state(&self) -> u16 {
+        self.keypad
+            .iter()
+            .enumerate()
+            .fold(0, |state, (idx, down)| state | ((*down as u16) << idx))
+    }
+
+    // Put the keypad in the given state as it stands between frames, with
+    // no keys that went down or up, like after loading a save state
+    pub fn restore(&mut self, state: u16) {
+        for (idx, key) in self.keypad.iter_mut().enumerate() {
+            *key = state & (1 << idx) != 0;
+        }
+        self.pressed = 0;
+        self.released = 0;
+        self.releasing = 0;
+    }
+
+    // Let go of keys that are down and press them again, like a player
+    // that does so between two frames
+    pub fn repress(&mut self, keys: u16) {
+        for key in 0..16 {
+            if keys & (1 << key) != 0 && self.keypad[key as usize] {
+                self.release_key(key);
+                self.press_key(key);
+            }
+        }
+    }
+
+    // Press and release keys until the keypad is in the given state
+    pub fn set_state(&mut self, state: u16) {
+        for key in 0..16 {
+            if state & (1 << key) != 0 {
+                self.press_key(key);
+            } else {
+                self.release_key(key);
+            }
+        }
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Keypad {
+        Keypad::new()
+    }
+}
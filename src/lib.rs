@@ -0,0 +1,148 @@
+pub mod asm;
+pub mod audio;
+pub mod bindings;
+pub mod bus;
+pub mod cpu;
+pub mod debugger;
+pub mod disasm;
+pub mod display;
+pub mod error;
+pub mod gdb;
+pub mod headless;
+pub mod instruction;
+pub mod keymap;
+pub mod keypad;
+pub mod memory;
+pub mod movie;
+pub mod quirks;
+pub mod rewind;
+pub mod savestate;
+pub mod trace;
+
+use bus::Bus;
+use cpu::{StepOutcome, CPU};
+use error::Chip8Error;
+use quirks::Quirks;
+use trace::{Snapshot, Trace};
+
+// The timers count down and the screen refreshes at 60 Hz
+pub const FRAME_RATE: u32 = 60;
+// Roughly the speed of the original interpreter, about 660 instructions
+// per second
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 11;
+
+pub struct Chip8 {
+    pub cpu: CPU,
+    pub bus: Bus,
+    // How many cpu operations run in each 60 Hz frame
+    pub instructions_per_frame: u32,
+    // Identifies the loaded ROM, see savestate::rom_hash
+    pub rom_hash: u64,
+    // Logs every instruction that completes
+    pub trace: Option<Trace>,
+    // Instructions executed since power on, steps that only wait for a key
+    // or the display do not count
+    pub instructions: u64,
+    // Ends the frame that run_frame is in once this many instructions have
+    // been executed, so that a run can be cut off in the middle of a frame
+    pub instruction_limit: Option<u64>,
+}
+
+impl Chip8 {
+    pub fn new(quirks: Quirks) -> Chip8 {
+        Chip8 {
+            cpu: CPU::new(quirks),
+            bus: Bus::new(),
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            rom_hash: savestate::rom_hash(&[]),
+            trace: None,
+            instructions: 0,
+            instruction_limit: None,
+        }
+    }
+
+    pub fn load_rom(&mut self, game_data: &[u8]) -> Result<(), Chip8Error> {
+        // The first 512 bytes are reserved for the interpreter
+        // After that the ROM is loaded
+        if cpu::PROGRAM_START as usize + game_data.len() > self.bus.ram.memory.len() {
+            return Err(Chip8Error::RomTooLarge {
+                size: game_data.len(),
+            });
+        }
+
+        for (idx, byte) in game_data.iter().enumerate() {
+            self.bus
+                .ram
+                .write_byte_to_ram(cpu::PROGRAM_START as usize + idx, *byte)?;
+        }
+        self.rom_hash = savestate::rom_hash(game_data);
+
+        Ok(())
+    }
+
+    // Run a single cpu operation
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        let result = match self.trace {
+            Some(ref mut trace) if trace.wants(self.cpu.pc) => {
+                let before = Snapshot::new(&self.cpu, &self.bus.ram.memory);
+                let result = self.cpu.process_operation(&mut self.bus);
+                match result {
+                    Ok(StepOutcome::Executed) | Ok(StepOutcome::Exited) => {
+                        trace.record(&before, &self.cpu)
+                    }
+                    Ok(_) => {}
+                    Err(ref e) => trace.record_error(&before, e),
+                }
+                result
+            }
+            _ => self.cpu.process_operation(&mut self.bus),
+        };
+        if let Ok(StepOutcome::Executed) = result {
+            self.instructions += 1;
+        }
+        result
+    }
+
+    // End a frame: count the timers down, this should happen at exactly
+    // 60 Hz, and start over with the key presses and releases
+    pub fn end_frame(&mut self) {
+        self.cpu.update_timers();
+        self.bus.keypad.next_frame();
+    }
+
+    // Run one 60 Hz frame: a batch of cpu operations followed by the end of
+    // the frame. The batch ends early once the cpu waits for the next frame
+    // to draw, has exited, or has reached the instruction limit
+    pub fn run_frame(&mut self) -> Result<StepOutcome, Chip8Error> {
+        let mut outcome = StepOutcome::Executed;
+        for _ in 0..self.instructions_per_frame {
+            if self.reached_instruction_limit() {
+                break;
+            }
+            outcome = self.step()?;
+            if let StepOutcome::WaitingForDisplay | StepOutcome::Exited = outcome {
+                break;
+            }
+        }
+
+        self.end_frame();
+        Ok(outcome)
+    }
+
+    pub fn reached_instruction_limit(&self) -> bool {
+        self.instruction_limit
+            .is_some_and(|limit| self.instructions >= limit)
+    }
+
+    // The buzzer sounds for as long as the sound timer is non-zero,
+    // it is up to the frontend to turn this into actual sound
+    pub fn is_buzzer_on(&self) -> bool {
+        self.cpu.st > 0
+    }
+}
+
+impl Default for Chip8 {
+    fn default() -> Chip8 {
+        Chip8::new(Quirks::default())
+    }
+}
@@ -1,75 +1,427 @@
-mod chip8;
+mod frontend;
+mod sound;
+mod tools;
 
+use chip8::audio::{Tone, Waveform};
+use chip8::bindings::Bindings;
+use chip8::debugger::Debugger;
+use chip8::display;
+use chip8::gdb::GdbStub;
+use chip8::keymap::Keymap;
+use chip8::movie::{Movie, MoviePlayer};
+use chip8::quirks::Quirks;
+use chip8::rewind::Rewind;
+use chip8::trace::Trace;
 use chip8::Chip8;
-use std::fs::File;
-use std::io::Read;
+use frontend::{Controls, DebugSession, Frontend, MovieMode};
+use sound::SoundPlayer;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 
 use std::env;
 
-use ggez;
-use ggez::audio;
 use ggez::event;
 use ggez::GameResult;
 
+// How much history the rewind key can go back through
+const DEFAULT_REWIND_SECONDS: u32 = 60;
+// Frames between two snapshots of the rewind history
+const REWIND_INTERVAL: u32 = 2;
+
+// Everything that can be set from the command line
+struct Options {
+    rom: String,
+    quirks: Quirks,
+    instructions_per_frame: u32,
+    tone: Tone,
+    seed: Option<u64>,
+    rewind_seconds: u32,
+    record: Option<String>,
+    play: Option<String>,
+    debug: bool,
+    gdb: Option<u16>,
+    trace: Option<String>,
+    trace_range: Option<RangeInclusive<u16>>,
+    trace_count: Option<u64>,
+    keymap: Option<String>,
+    layout: Option<String>,
+    bindings: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut rom = None;
+    let mut quirks = Quirks::default();
+    let mut instructions_per_frame = chip8::DEFAULT_INSTRUCTIONS_PER_FRAME;
+    let mut tone = Tone::default();
+    let mut seed = None;
+    let mut rewind_seconds = DEFAULT_REWIND_SECONDS;
+    let mut record = None;
+    let mut play = None;
+    let mut debug = false;
+    let mut gdb = None;
+    let mut trace = None;
+    let mut trace_range = None;
+    let mut trace_count = None;
+    let mut keymap = None;
+    let mut layout = None;
+    let mut bindings = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let value = iter.next().ok_or("--quirks needs a preset")?;
+                quirks = value.parse()?;
+            }
+            "--ipf" => {
+                let value = iter.next().ok_or("--ipf needs a number")?;
+                instructions_per_frame = value
+                    .parse()
+                    .map_err(|_| format!("invalid instructions per frame '{}'", value))?;
+            }
+            "--seed" => {
+                let value = iter.next().ok_or("--seed needs a number")?;
+                seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{}'", value))?,
+                );
+            }
+            "--rewind" => {
+                let value = iter.next().ok_or("--rewind needs a number of seconds")?;
+                rewind_seconds = value
+                    .parse()
+                    .map_err(|_| format!("invalid number of seconds '{}'", value))?;
+            }
+            "--record" => {
+                record = Some(iter.next().ok_or("--record needs a file")?.clone());
+            }
+            "--play" => {
+                play = Some(iter.next().ok_or("--play needs a file")?.clone());
+            }
+            "--debug" => debug = true,
+            "--gdb" => {
+                let value = iter.next().ok_or("--gdb needs a port")?;
+                gdb = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid port '{}'", value))?,
+                );
+            }
+            "--trace" => {
+                trace = Some(iter.next().ok_or("--trace needs a file")?.clone());
+            }
+            "--trace-range" => {
+                let value = iter.next().ok_or("--trace-range needs START-END")?;
+                trace_range = Some(Trace::parse_range(value)?);
+            }
+            "--trace-count" => {
+                let value = iter.next().ok_or("--trace-count needs a number")?;
+                trace_count = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid number of instructions '{}'", value))?,
+                );
+            }
+            "--keymap" => {
+                keymap = Some(iter.next().ok_or("--keymap needs a file")?.clone());
+            }
+            "--layout" => {
+                layout = Some(iter.next().ok_or("--layout needs a layout")?.clone());
+            }
+            "--bindings" => {
+                bindings = Some(iter.next().ok_or("--bindings needs a file")?.clone());
+            }
+            "--tone" => {
+                let value = iter.next().ok_or("--tone needs a frequency")?;
+                tone.frequency = value
+                    .parse()
+                    .map_err(|_| format!("invalid frequency '{}'", value))?;
+            }
+            "--waveform" => {
+                let value = iter.next().ok_or("--waveform needs a waveform")?;
+                tone.waveform = value.parse::<Waveform>()?;
+            }
+            "--volume" => {
+                let value = iter.next().ok_or("--volume needs a number")?;
+                tone.volume = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("invalid volume '{}'", value))?
+                    .clamp(0.0, 1.0);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    Ok(Options {
+        rom: rom.ok_or("no ROM file given")?,
+        quirks,
+        instructions_per_frame,
+        tone,
+        seed,
+        rewind_seconds,
+        record,
+        play,
+        debug,
+        gdb,
+        trace,
+        trace_range,
+        trace_count,
+        keymap,
+        layout,
+        bindings,
+    })
+}
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [options] <path_to_rom>", program);
+    tools::print_usage(program);
+    eprintln!("Example: {} games/PONG.ch8", program);
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --quirks <preset>  legacy, vip, chip48 or schip (default: legacy)");
+    eprintln!(
+        "  --ipf <n>          instructions per 60 Hz frame (default: {})",
+        chip8::DEFAULT_INSTRUCTIONS_PER_FRAME
+    );
+    eprintln!("  --seed <n>         seed for the random number generator");
+    eprintln!(
+        "  --rewind <s>       seconds of rewind history, 0 to disable (default: {})",
+        DEFAULT_REWIND_SECONDS
+    );
+    eprintln!("  --record <file>    record the keypad into a movie file");
+    eprintln!("  --play <file>      play back a movie file");
+    eprintln!("  --debug            start paused in the terminal debugger");
+    eprintln!("  --gdb <port>       start paused and wait for gdb on a local port");
+    eprintln!("  --trace <file>     log every executed instruction to a file");
+    eprintln!("  --trace-range <r>  only log instructions in an address range like 200-2FF");
+    eprintln!("  --trace-count <n>  stop logging after n instructions");
+    eprintln!("  --keymap <file>    remap the keypad with a .c8k file (default: next to the ROM)");
+    eprintln!("  --layout <name>    qwerty, azerty or numpad keyboard layout (default: qwerty)");
+    eprintln!("  --bindings <file>  bind keys and gamepads from a file (default: next to the ROM)");
+    eprintln!("  --tone <hz>        frequency of the buzzer (default: 440)");
+    eprintln!("  --waveform <wave>  square, triangle, sawtooth or sine (default: square)");
+    eprintln!("  --volume <0-1>     volume of the buzzer (default: 0.25)");
+}
+
+// The keymap given on the command line, or else the .c8k file next to the
+// ROM if there is one
+fn load_keymap(options: &Options, rom: &Path) -> Result<Keymap, String> {
+    let path = match options.keymap {
+        Some(ref path) => PathBuf::from(path),
+        None => rom.with_extension("c8k"),
+    };
+    if options.keymap.is_none() && !path.exists() {
+        return Ok(Keymap::default());
+    }
+
+    let keymap = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| text.parse())
+        .map_err(|e| format!("'{}': {}", path.display(), e))?;
+    println!("Using keymap {} from {}", keymap, path.display());
+    Ok(keymap)
+}
+
+// The keyboard layout, with the bindings file given on the command line or
+// else the .bindings file next to the ROM on top if there is one
+fn load_bindings(options: &Options, rom: &Path) -> Result<Bindings, String> {
+    let mut bindings = match options.layout {
+        Some(ref layout) => Bindings::layout(layout)?,
+        None => Bindings::default(),
+    };
+    let path = match options.bindings {
+        Some(ref path) => PathBuf::from(path),
+        None => rom.with_extension("bindings"),
+    };
+    if options.bindings.is_none() && !path.exists() {
+        return Ok(bindings);
+    }
+
+    fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| bindings.update(&text))
+        .map_err(|e| format!("'{}': {}", path.display(), e))?;
+    println!("Using bindings from {}", path.display());
+    Ok(bindings)
+}
+
 fn main() -> GameResult {
     // Get the game the player wants to play
     let args: Vec<String> = env::args().collect();
-    
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path_to_rom>", args[0]);
-        eprintln!("Example: {} games/PONG.ch8", args[0]);
-        std::process::exit(1);
+    if let Some(code) = tools::run(&args) {
+        std::process::exit(code);
     }
-    
-    let game = &args[1];
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            print_usage(&args[0]);
+            std::process::exit(1);
+        }
+    };
+
+    let game = &options.rom;
     println!("Loading game: {}", game);
 
     // Determine the dimensions of the window
-    let width = chip8::display::PIXEL_SIZE as f32 * chip8::display::WIDTH as f32;
-    let height = chip8::display::PIXEL_SIZE as f32 * chip8::display::HEIGHT as f32;
+    let width = display::PIXEL_SIZE as f32 * display::WIDTH as f32;
+    let height = display::PIXEL_SIZE as f32 * display::HEIGHT as f32;
 
-    let (mut ctx, event_loop) =
-        ggez::ContextBuilder::new(&("CHIP-8 ".to_owned() + &args[1]), "Abe")
-            .window_setup(
-                ggez::conf::WindowSetup::default().title(&("CHIP-8: ".to_owned() + &args[1])),
-            )
-            .window_mode(ggez::conf::WindowMode::default().dimensions(width, height))
-            .build()?;
+    let (ctx, event_loop) = ggez::ContextBuilder::new(&("CHIP-8 ".to_owned() + game), "Abe")
+        .window_setup(
+            // Rendering is synced to the display, emulation itself runs on
+            // its own fixed 60 Hz timestep
+            ggez::conf::WindowSetup::default()
+                .title(&("CHIP-8: ".to_owned() + game))
+                .vsync(true),
+        )
+        .window_mode(ggez::conf::WindowMode::default().dimensions(width, height))
+        .build()?;
 
     // Read the game into memory
-    let mut file = match File::open(&args[1]) {
+    let mut file = match File::open(game) {
         Ok(file) => file,
         Err(e) => {
-            eprintln!("Error opening ROM file '{}': {}", args[1], e);
+            eprintln!("Error opening ROM file '{}': {}", game, e);
             std::process::exit(1);
         }
     };
-    
+
     let mut rom_data = Vec::<u8>::new();
     if let Err(e) = file.read_to_end(&mut rom_data) {
-        eprintln!("Error reading ROM file '{}': {}", args[1], e);
+        eprintln!("Error reading ROM file '{}': {}", game, e);
         std::process::exit(1);
     }
 
-    // Get the audio file from the resources folder
-    let audio_file = match audio::Source::new(&mut ctx, "/beep.wav") {
-        Ok(audio) => {
-            println!("Audio loaded successfully");
-            Some(audio)
-        },
+    // The buzzer is synthesized, so there is nothing to load
+    let sound = match SoundPlayer::new(&ctx, options.tone) {
+        Ok(sound) => Some(sound),
         Err(e) => {
-            eprintln!("Warning: Could not load audio file '/beep.wav': {}", e);
+            eprintln!("Warning: Could not open audio stream: {}", e);
             eprintln!("Continuing without audio...");
             None
         }
     };
 
     // Initialize chip8 VM
-    let mut chip8 = Chip8::new(audio_file);
+    let mut chip8 = Chip8::new(options.quirks);
+    chip8.instructions_per_frame = options.instructions_per_frame;
 
     // Load the game into the RAM
-    chip8.load_rom(&mut rom_data);
+    if let Err(e) = chip8.load_rom(&rom_data) {
+        eprintln!("Error loading ROM file '{}': {}", game, e);
+        std::process::exit(1);
+    }
+
+    // Always seed the random numbers, so that any run can be reproduced
+    // by passing the same seed again
+    let seed = options.seed.unwrap_or_else(rand::random);
+    println!("Random seed: {}", seed);
+    chip8.cpu.seed_rng(seed);
+
+    if let Some(ref path) = options.trace {
+        let file = match File::create(path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Error creating trace file '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        let mut trace = Trace::new(Box::new(BufWriter::new(file)));
+        if let Some(ref range) = options.trace_range {
+            trace.set_range(range.clone());
+        }
+        if let Some(count) = options.trace_count {
+            trace.set_limit(count);
+        }
+        chip8.trace = Some(trace);
+    }
+
+    // A movie that is played back brings its own settings
+    let movie = if let Some(ref path) = options.play {
+        let movie = match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Movie::parse(&text))
+        {
+            Ok(movie) => movie,
+            Err(e) => {
+                eprintln!("Error reading movie file '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        };
+        if let Err(e) = movie.apply_settings(&mut chip8) {
+            eprintln!("Error playing movie file '{}': {}", path, e);
+            std::process::exit(1);
+        }
+        println!("Playing movie {}", path);
+        Some(MovieMode::Play(MoviePlayer::new(movie)))
+    } else {
+        options.record.as_ref().map(|path| {
+            println!("Recording movie to {}", path);
+            MovieMode::Record {
+                movie: Movie::new(&chip8, seed),
+                path: PathBuf::from(path),
+            }
+        })
+    };
+
+    // Games that want their keys somewhere else come with a keymap, and
+    // games for two players with bindings for two gamepads
+    let keymap = match load_keymap(&options, Path::new(game)) {
+        Ok(keymap) => keymap,
+        Err(e) => {
+            eprintln!("Error reading keymap: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let bindings = match load_bindings(&options, Path::new(game)) {
+        Ok(bindings) => bindings,
+        Err(e) => {
+            eprintln!("Error reading bindings: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Both debuggers start with the machine paused
+    let gdb = options
+        .gdb
+        .map(|port| match GdbStub::bind(("127.0.0.1", port)) {
+            Ok(gdb) => {
+                println!("Waiting for gdb on 127.0.0.1:{}", port);
+                gdb
+            }
+            Err(e) => {
+                eprintln!("Error listening for gdb on port {}: {}", port, e);
+                std::process::exit(1);
+            }
+        });
+    if options.debug {
+        println!("Debugger enabled, the machine is paused. Type help for a list of commands");
+    }
+    let debug = if options.debug || gdb.is_some() {
+        Some(DebugSession::new(Debugger::new(), options.debug, gdb))
+    } else {
+        None
+    };
 
     // Start the chip8 machine
-    event::run(ctx, event_loop, chip8)
+    event::run(
+        ctx,
+        event_loop,
+        Frontend::new(
+            chip8,
+            sound,
+            Path::new(game),
+            Rewind::new(options.rewind_seconds, REWIND_INTERVAL),
+            movie,
+            debug,
+            Controls { bindings, keymap },
+        ),
+    )
 }
@@ -0,0 +1,105 @@
+use crate::error::Chip8Error;
+
+// Where the hardcoded font sprites are stored in the interpreter area
+pub const FONT_ADDRESS: u16 = 0x000;
+pub const BIG_FONT_ADDRESS: u16 = 0x050;
+
+// XO-CHIP extends the original 4 KiB of memory to the full
+// 64 KiB that a 16 bit I register can address
+pub const MEMORY_SIZE: usize = 0x10000;
+
+pub struct RAM {
+    pub memory: Vec<u8>,
+}
+
+impl RAM {
+    pub fn new() -> RAM {
+        let mut ram = RAM {
+            memory: vec![0; MEMORY_SIZE],
+        };
+
+        // The interpreter has a few hardcoded sprites
+        // representing the range of 0 to F (hexadecimal)
+        let sprites: [[u8; 5]; 16] = [
+            [0xF0, 0x90, 0x90, 0x90, 0xF0],
+            [0x20, 0x60, 0x20, 0x20, 0x70],
+            [0xF0, 0x10, 0xF0, 0x80, 0xF0],
+            [0xF0, 0x10, 0xF0, 0x10, 0xF0],
+            [0x90, 0x90, 0xF0, 0x10, 0x10],
+            [0xF0, 0x80, 0xF0, 0x10, 0xF0],
+            [0xF0, 0x80, 0xF0, 0x90, 0xF0],
+            [0xF0, 0x10, 0x20, 0x40, 0x40],
+            [0xF0, 0x90, 0xF0, 0x90, 0xF0],
+            [0xF0, 0x90, 0xF0, 0x10, 0xF0],
+            [0xF0, 0x90, 0xF0, 0x90, 0x90],
+            [0xE0, 0x90, 0xE0, 0x90, 0xE0],
+            [0xF0, 0x80, 0x80, 0x80, 0xF0],
+            [0xE0, 0x90, 0x90, 0x90, 0xE0],
+            [0xF0, 0x80, 0xF0, 0x80, 0xF0],
+            [0xF0, 0x80, 0xF0, 0x80, 0x80],
+        ];
+
+        // SUPER-CHIP adds a larger 8x10 font, its digits A to F
+        // come from XO-CHIP
+        let big_sprites: [[u8; 10]; 16] = [
+            [0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C],
+            [0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C],
+            [0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF],
+            [0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C],
+            [0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06],
+            [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C],
+            [0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C],
+            [0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60],
+            [0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C],
+            [0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C],
+            [0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3],
+            [0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC],
+            [0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C],
+            [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC],
+            [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF],
+            [0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0],
+        ];
+
+        // Put those characters in the ram
+        let mut idx = FONT_ADDRESS as usize;
+        for sprite in sprites.iter() {
+            for ch in sprite {
+                ram.memory[idx] = *ch;
+                idx += 1;
+            }
+        }
+
+        let mut idx = BIG_FONT_ADDRESS as usize;
+        for sprite in big_sprites.iter() {
+            for ch in sprite {
+                ram.memory[idx] = *ch;
+                idx += 1;
+            }
+        }
+
+        ram
+    }
+
+    pub fn read_byte_from_ram(&self, address: usize) -> Result<u8, Chip8Error> {
+        self.memory
+            .get(address)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr: address })
+    }
+
+    pub fn write_byte_to_ram(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        match self.memory.get_mut(address) {
+            Some(byte) => {
+                *byte = value;
+                Ok(())
+            }
+            None => Err(Chip8Error::MemoryOutOfBounds { addr: address }),
+        }
+    }
+}
+
+impl Default for RAM {
+    fn default() -> RAM {
+        RAM::new()
+    }
+}
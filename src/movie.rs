@@ -0,0 +1,217 @@
+use crate::quirks::Quirks;
+use crate::Chip8;
+use std::fmt;
+
+// An input movie is everything needed to replay a run exactly: the settings
+// of the machine at power on and the state of the keypad for every frame.
+// The file is plain text, one line per keypad change
+//
+//   chip8-movie 2
+//   rom 1f2e3d4c5b6a7988
+//   seed 1234
+//   ipf 11
+//   quirks shift=true load_store=false ...
+//   frames 3600
+//   120 0010
+//   135 0000
+//   150 0010
+//   164 0010 0010
+//
+// where each of the last lines is a frame number and the keypad state from
+// that frame on, as a hexadecimal bitmask (see Keypad::state). A third
+// bitmask lists the keys that were let go of and pressed again before the
+// frame, so they stayed down but FX0A still sees them go down.
+//
+// Version 1 movies were recorded before the key_release quirk existed and
+// do not list it, it keeps its value from the default preset.
+const MAGIC: &str = "chip8-movie";
+pub const FORMAT_VERSION: u32 = 2;
+
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub instructions_per_frame: u32,
+    pub quirks: Quirks,
+    // The number of frames in the movie
+    pub frames: u64,
+    // The frames on which the keypad changed, its new state and the keys
+    // that were pressed again while they stayed down
+    pub changes: Vec<(u64, u16, u16)>,
+}
+
+impl Movie {
+    // Start recording a movie of a machine that has just been set up
+    pub fn new(chip8: &Chip8, seed: u64) -> Movie {
+        Movie {
+            rom_hash: chip8.rom_hash,
+            seed,
+            instructions_per_frame: chip8.instructions_per_frame,
+            quirks: chip8.cpu.quirks,
+            frames: 0,
+            changes: Vec::new(),
+        }
+    }
+
+    // Record the keypad state that the next frame runs with, and the keys
+    // that went down since the last frame (see Keypad::pressed)
+    pub fn record(&mut self, keys: u16, pressed: u16) {
+        let last = self.changes.last().map_or(0, |(_, keys, _)| *keys);
+        let repressed = pressed & keys & last;
+        if keys != last || repressed != 0 {
+            self.changes.push((self.frames, keys, repressed));
+        }
+        self.frames += 1;
+    }
+
+    // Give a freshly loaded machine the settings the movie was recorded with
+    pub fn apply_settings(&self, chip8: &mut Chip8) -> Result<(), String> {
+        if chip8.rom_hash != self.rom_hash {
+            return Err(format!(
+                "movie was recorded with ROM {:016x}, but ROM {:016x} is loaded",
+                self.rom_hash, chip8.rom_hash
+            ));
+        }
+
+        chip8.cpu.quirks = self.quirks;
+        chip8.cpu.seed_rng(self.seed);
+        chip8.instructions_per_frame = self.instructions_per_frame;
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let mut header = |name: &str| -> Result<String, String> {
+            let (number, line) = lines
+                .next()
+                .ok_or(format!("movie ends before its '{}' line", name))?;
+            let mut parts = line.splitn(2, ' ');
+            if parts.next() != Some(name) {
+                return Err(format!("line {}: expected '{}'", number, name));
+            }
+            Ok(parts.next().unwrap_or("").to_string())
+        };
+
+        let version = header(MAGIC)?;
+        match version.parse() {
+            Ok(1..=FORMAT_VERSION) => {}
+            _ => return Err(format!("unsupported movie version {}", version)),
+        }
+        let rom = header("rom")?;
+        let rom_hash =
+            u64::from_str_radix(&rom, 16).map_err(|_| format!("invalid ROM hash '{}'", rom))?;
+        let seed = header("seed")?;
+        let seed = seed
+            .parse()
+            .map_err(|_| format!("invalid seed '{}'", seed))?;
+        let ipf = header("ipf")?;
+        let instructions_per_frame = ipf
+            .parse()
+            .map_err(|_| format!("invalid instructions per frame '{}'", ipf))?;
+        let mut quirks = Quirks::default();
+        for flag in header("quirks")?.split_whitespace() {
+            let mut parts = flag.splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            let value = parts
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or(format!("invalid quirk '{}'", flag))?;
+            quirks.set(name, value)?;
+        }
+        let frames = header("frames")?;
+        let frames = frames
+            .parse()
+            .map_err(|_| format!("invalid number of frames '{}'", frames))?;
+
+        let mut changes = Vec::new();
+        for (number, line) in lines {
+            let mut parts = line.split_whitespace();
+            let frame = parts.next().and_then(|frame| frame.parse().ok());
+            let keys = parts
+                .next()
+                .and_then(|keys| u16::from_str_radix(keys, 16).ok());
+            let repressed = match parts.next() {
+                Some(keys) => u16::from_str_radix(keys, 16).ok(),
+                None => Some(0),
+            };
+            match (frame, keys, repressed, parts.next()) {
+                (Some(frame), Some(keys), Some(repressed), None) => {
+                    changes.push((frame, keys, repressed))
+                }
+                _ => return Err(format!("line {}: invalid keypad change '{}'", number, line)),
+            }
+        }
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            instructions_per_frame,
+            quirks,
+            frames,
+            changes,
+        })
+    }
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", MAGIC, FORMAT_VERSION)?;
+        writeln!(f, "rom {:016x}", self.rom_hash)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "ipf {}", self.instructions_per_frame)?;
+        writeln!(f, "quirks {}", self.quirks)?;
+        writeln!(f, "frames {}", self.frames)?;
+        for (frame, keys, repressed) in self.changes.iter() {
+            if *repressed != 0 {
+                writeln!(f, "{} {:04x} {:04x}", frame, keys, repressed)?;
+            } else {
+                writeln!(f, "{} {:04x}", frame, keys)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Plays a movie back by handing out the keypad state for each frame
+pub struct MoviePlayer {
+    movie: Movie,
+    frame: u64,
+    next_change: usize,
+    keys: u16,
+}
+
+impl MoviePlayer {
+    pub fn new(movie: Movie) -> MoviePlayer {
+        MoviePlayer {
+            movie,
+            frame: 0,
+            next_change: 0,
+            keys: 0,
+        }
+    }
+
+    // The keypad state for the next frame and the keys to press again in
+    // it (see Keypad::repress), None once the movie is over
+    pub fn next_frame(&mut self) -> Option<(u16, u16)> {
+        if self.frame >= self.movie.frames {
+            return None;
+        }
+
+        let mut repressed = 0;
+        while let Some((frame, keys, again)) = self.movie.changes.get(self.next_change) {
+            if *frame > self.frame {
+                break;
+            }
+            self.keys = *keys;
+            repressed = *again;
+            self.next_change += 1;
+        }
+        self.frame += 1;
+
+        Some((self.keys, repressed))
+    }
+}
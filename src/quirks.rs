@@ -0,0 +1,147 @@
+use std::fmt;
+use std::str::FromStr;
+
+// The original CHIP-8 interpreter and its later reimplementations disagree
+// on what some of the instructions do. These flags select between the
+// interpretations, every ROM expects the behaviour of the interpreter it was
+// written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift Vx in place and ignore Vy,
+    // instead of storing the shifted Vy in Vx
+    pub shift: bool,
+    // FX55 and FX65 leave I pointing after the last register they touched
+    pub load_store: bool,
+    // BNNN jumps to NNN + Vx, where x is the highest nibble of NNN,
+    // instead of NNN + V0
+    pub jump: bool,
+    // 8XY1, 8XY2 and 8XY3 reset VF to zero
+    pub vf_reset: bool,
+    // Sprites are clipped at the edges of the screen instead of wrapping
+    // around to the other side
+    pub clipping: bool,
+    // DXYN in low resolution waits for the start of the next frame before
+    // drawing, so only one sprite can be drawn each frame
+    pub display_wait: bool,
+    // FX0A waits for the key to be released again before it stores it,
+    // instead of taking it as soon as it goes down
+    pub key_release: bool,
+}
+
+impl Quirks {
+    // What this emulator did before the quirks could be chosen, which the
+    // games in games/ were tested with: CHIP-48 shifts and loads, but BNNN
+    // jumps from V0 and sprites wrap around the edges
+    pub fn legacy() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: false,
+            jump: false,
+            vf_reset: false,
+            clipping: false,
+            display_wait: false,
+            key_release: false,
+        }
+    }
+
+    // The interpreter on the RCA COSMAC VIP that CHIP-8 started on
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift: false,
+            load_store: true,
+            jump: false,
+            vf_reset: true,
+            clipping: true,
+            display_wait: true,
+            key_release: true,
+        }
+    }
+
+    // CHIP-48 on the HP-48 calculators, which most of the later
+    // interpreters copied
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: false,
+            jump: true,
+            vf_reset: false,
+            clipping: true,
+            display_wait: false,
+            key_release: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1, the successor of CHIP-48. Its low resolution mode
+    // still draws in step with the display like the VIP did
+    pub fn super_chip() -> Quirks {
+        Quirks {
+            shift: true,
+            load_store: false,
+            jump: true,
+            vf_reset: false,
+            clipping: true,
+            display_wait: true,
+            key_release: false,
+        }
+    }
+}
+
+impl Quirks {
+    // Turn a single quirk on or off by the name it is displayed with
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), String> {
+        let flag = match name {
+            "shift" => &mut self.shift,
+            "load_store" => &mut self.load_store,
+            "jump" => &mut self.jump,
+            "vf_reset" => &mut self.vf_reset,
+            "clipping" => &mut self.clipping,
+            "display_wait" => &mut self.display_wait,
+            "key_release" => &mut self.key_release,
+            _ => return Err(format!("unknown quirk '{}'", name)),
+        };
+        *flag = value;
+        Ok(())
+    }
+}
+
+impl Default for Quirks {
+    // Games keep running the way they always did, the other presets are
+    // opt-in
+    fn default() -> Quirks {
+        Quirks::legacy()
+    }
+}
+
+// The named presets, as they are written on the command line
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Quirks, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "legacy" => Ok(Quirks::legacy()),
+            "vip" | "cosmac" | "chip8" => Ok(Quirks::cosmac_vip()),
+            "chip48" => Ok(Quirks::chip48()),
+            "schip" | "superchip" => Ok(Quirks::super_chip()),
+            _ => Err(format!(
+                "unknown quirks preset '{}', expected one of legacy, vip, chip48, schip",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "shift={} load_store={} jump={} vf_reset={} clipping={} display_wait={} key_release={}",
+            self.shift,
+            self.load_store,
+            self.jump,
+            self.vf_reset,
+            self.clipping,
+            self.display_wait,
+            self.key_release
+        )
+    }
+}
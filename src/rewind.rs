@@ -0,0 +1,167 @@
+use crate::savestate::StateError;
+use crate::Chip8;
+use std::collections::VecDeque;
+
+// Keeps a history of snapshots of the machine so gameplay can be stepped
+// backwards. Only the newest snapshot is kept in full, every older one is
+// stored as the compressed difference with the snapshot after it. Most of
+// the machine does not change between two snapshots, so a minute of
+// history only takes a few megabytes.
+pub struct Rewind {
+    // The newest snapshot
+    current: Option<Vec<u8>>,
+    // Going back from the newest snapshot, each delta turns a snapshot into
+    // the one before it. The oldest delta is at the front
+    deltas: VecDeque<Vec<u8>>,
+    // How many snapshots to keep at most
+    capacity: usize,
+    // A snapshot is taken every this many frames
+    interval: u32,
+    frames: u32,
+}
+
+impl Rewind {
+    // Keep the given number of seconds of history, with a snapshot
+    // every interval frames
+    pub fn new(seconds: u32, interval: u32) -> Rewind {
+        let interval = interval.max(1);
+        Rewind {
+            current: None,
+            deltas: VecDeque::new(),
+            capacity: (seconds * crate::FRAME_RATE / interval) as usize,
+            interval,
+            frames: 0,
+        }
+    }
+
+    // Call once every frame, takes a snapshot when it is time for one
+    pub fn capture(&mut self, chip8: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.frames += 1;
+        if self.frames < self.interval {
+            return;
+        }
+        self.frames = 0;
+
+        let snapshot = chip8.snapshot();
+        if let Some(previous) = self.current.take() {
+            self.deltas.push_back(encode_delta(&previous, &snapshot));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.current = Some(snapshot);
+    }
+
+    // Step the machine back to the previous snapshot,
+    // false once the history has run out
+    pub fn rewind(&mut self, chip8: &mut Chip8) -> Result<bool, StateError> {
+        let current = match self.current {
+            Some(ref mut current) => current,
+            None => return Ok(false),
+        };
+
+        let stepped = match self.deltas.pop_back() {
+            Some(delta) => {
+                apply_delta(&delta, current);
+                true
+            }
+            None => false,
+        };
+
+        chip8.restore_snapshot(current)?;
+        self.frames = 0;
+        Ok(stepped)
+    }
+
+    // How many seconds of history there are
+    pub fn seconds(&self) -> f32 {
+        (self.deltas.len() as u32 * self.interval) as f32 / crate::FRAME_RATE as f32
+    }
+
+    // The number of bytes the history takes
+    pub fn memory_usage(&self) -> usize {
+        self.current.as_ref().map_or(0, |current| current.len())
+            + self.deltas.iter().map(|delta| delta.len()).sum::<usize>()
+    }
+}
+
+// The XOR of two snapshots of the same size, as a list of runs. Each run is
+// the number of unchanged bytes followed by the number of changed bytes and
+// those changed bytes themselves, both counts as LEB128 numbers
+pub fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut delta = Vec::new();
+    let mut position = 0;
+
+    while position < to.len() {
+        let unchanged = from[position..]
+            .iter()
+            .zip(&to[position..])
+            .take_while(|(a, b)| a == b)
+            .count();
+        position += unchanged;
+        if position == to.len() {
+            break;
+        }
+
+        let changed = from[position..]
+            .iter()
+            .zip(&to[position..])
+            .take_while(|(a, b)| a != b)
+            .count();
+        write_number(&mut delta, unchanged);
+        write_number(&mut delta, changed);
+        delta.extend(
+            from[position..position + changed]
+                .iter()
+                .zip(&to[position..position + changed])
+                .map(|(a, b)| a ^ b),
+        );
+        position += changed;
+    }
+
+    delta
+}
+
+// The XOR works in both directions, a delta from a to b also turns b into a
+pub fn apply_delta(delta: &[u8], snapshot: &mut [u8]) {
+    let mut input = delta.iter();
+    let mut position = 0;
+
+    while let Some(unchanged) = read_number(&mut input) {
+        position += unchanged;
+        let changed = read_number(&mut input).unwrap_or(0);
+        for byte in snapshot[position..position + changed].iter_mut() {
+            *byte ^= input.next().copied().unwrap_or(0);
+        }
+        position += changed;
+    }
+}
+
+fn write_number(output: &mut Vec<u8>, mut number: usize) {
+    loop {
+        let byte = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+fn read_number<'a>(input: &mut impl Iterator<Item = &'a u8>) -> Option<usize> {
+    let mut number = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *input.next()?;
+        number |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(number);
+        }
+        shift += 7;
+    }
+}
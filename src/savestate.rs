@@ -0,0 +1,237 @@
+use crate::audio::PATTERN_SIZE;
+use crate::cpu::KeyWait;
+use crate::display::PLANES;
+use crate::Chip8;
+use std::error::Error;
+use std::fmt;
+
+// A save state file is a header followed by a snapshot of the machine
+//
+//   magic    4 bytes  "C8SS"
+//   version  u16      FORMAT_VERSION
+//   rom hash u64      rom_hash() of the ROM the state was saved from
+//   snapshot          see Chip8::snapshot
+//
+// All numbers are little endian. The random number generator and the
+// settings of the machine (quirks, speed) are not part of the state.
+const MAGIC: &[u8; 4] = b"C8SS";
+pub const FORMAT_VERSION: u16 = 2;
+const HEADER_SIZE: usize = 4 + 2 + 8;
+
+// The snapshot has a fixed size, so it can be checked before anything
+// of the running machine is overwritten
+const CPU_SIZE: usize = 16 + 2 + 2 + 16 * 2 + 2 + 1 + 1 + 1 + PATTERN_SIZE + 1 + 16 + 2 + 1 + 3;
+const RAM_SIZE: usize = crate::memory::MEMORY_SIZE;
+const DISPLAY_SIZE: usize = crate::display::HIRES_WIDTH * crate::display::HIRES_HEIGHT + 1 + 1;
+const KEYPAD_SIZE: usize = 16;
+// Where the stack pointer and the FX0A wait are in the cpu
+const SP_OFFSET: usize = 16 + 2 + 2 + 16 * 2;
+const KEY_WAIT_OFFSET: usize = CPU_SIZE - 3;
+pub const SNAPSHOT_SIZE: usize = CPU_SIZE + RAM_SIZE + DISPLAY_SIZE + KEYPAD_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    // The data does not start with the save state magic
+    NotASaveState,
+    // The state was written by a newer or older format
+    UnsupportedVersion(u16),
+    // The state belongs to another ROM than the one that is loaded
+    WrongRom { expected: u64, found: u64 },
+    // The data ends before the snapshot does
+    Truncated,
+    // A value in the snapshot that the machine can not have
+    Invalid(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::NotASaveState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::WrongRom { expected, found } => write!(
+                f,
+                "save state is for ROM {:016x}, but ROM {:016x} is loaded",
+                found, expected
+            ),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid(what) => write!(f, "save state has an invalid {}", what),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+// 64 bit FNV-1a hash, identifies the ROM a state belongs to
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+impl Chip8 {
+    // A save state of the whole machine, to be written to disk
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(HEADER_SIZE + SNAPSHOT_SIZE);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(&self.rom_hash.to_le_bytes());
+        data.extend_from_slice(&self.snapshot());
+        data
+    }
+
+    // Restore a save state, it has to belong to the loaded ROM
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        if data.len() < HEADER_SIZE || &data[0..4] != MAGIC {
+            return Err(StateError::NotASaveState);
+        }
+
+        let mut reader = Reader { data, position: 4 };
+        let version = reader.u16();
+        if version != FORMAT_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let hash = reader.u64();
+        if hash != self.rom_hash {
+            return Err(StateError::WrongRom {
+                expected: self.rom_hash,
+                found: hash,
+            });
+        }
+
+        self.restore_snapshot(&data[HEADER_SIZE..])
+    }
+
+    // The state of the cpu, memory, display and keypad
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(SNAPSHOT_SIZE);
+
+        let cpu = &self.cpu;
+        data.extend_from_slice(&cpu.gp);
+        data.extend_from_slice(&cpu.pc.to_le_bytes());
+        data.extend_from_slice(&cpu.i.to_le_bytes());
+        for entry in cpu.stack.iter() {
+            data.extend_from_slice(&entry.to_le_bytes());
+        }
+        data.extend_from_slice(&cpu.sp.to_le_bytes());
+        data.push(cpu.dt);
+        data.push(cpu.st);
+        data.push(cpu.audio_pattern.is_some() as u8);
+        data.extend_from_slice(&cpu.audio_pattern.unwrap_or([0; PATTERN_SIZE]));
+        data.push(cpu.pitch);
+        data.extend_from_slice(&cpu.rpl);
+        data.extend_from_slice(&cpu.operand.to_le_bytes());
+        data.push(cpu.vblank as u8);
+        // What FX0A waits for: nothing, a press or a release, then x and the key
+        let (wait, x, key) = match cpu.key_wait {
+            None => (0, 0, 0),
+            Some(KeyWait::Press { x }) => (1, x, 0),
+            Some(KeyWait::Release { x, key }) => (2, x, key),
+        };
+        data.extend_from_slice(&[wait, x, key]);
+
+        data.extend_from_slice(&self.bus.ram.memory);
+
+        let display = &self.bus.display;
+        data.extend_from_slice(&display.gfx);
+        data.push(display.hires as u8);
+        data.push(display.planes);
+
+        data.extend(self.bus.keypad.keypad.iter().map(|key| *key as u8));
+
+        data
+    }
+
+    // Put the machine back in the state of a snapshot
+    pub fn restore_snapshot(&mut self, data: &[u8]) -> Result<(), StateError> {
+        if data.len() < SNAPSHOT_SIZE {
+            return Err(StateError::Truncated);
+        }
+
+        // Check the values the cpu indexes with before anything is changed
+        let mut reader = Reader {
+            data,
+            position: SP_OFFSET,
+        };
+        if reader.u16() as usize > self.cpu.stack.len() {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        reader.position = KEY_WAIT_OFFSET;
+        let (wait, x, key) = (reader.u8(), reader.u8(), reader.u8());
+        if wait > 2 || x > 0xF || key > 0xF {
+            return Err(StateError::Invalid("key wait"));
+        }
+
+        let mut reader = Reader { data, position: 0 };
+        let cpu = &mut self.cpu;
+        cpu.gp.copy_from_slice(reader.bytes(16));
+        cpu.pc = reader.u16();
+        cpu.i = reader.u16();
+        for entry in cpu.stack.iter_mut() {
+            *entry = reader.u16();
+        }
+        cpu.sp = reader.u16();
+        cpu.dt = reader.u8();
+        cpu.st = reader.u8();
+        let has_pattern = reader.u8() != 0;
+        let mut pattern = [0; PATTERN_SIZE];
+        pattern.copy_from_slice(reader.bytes(PATTERN_SIZE));
+        cpu.audio_pattern = if has_pattern { Some(pattern) } else { None };
+        cpu.pitch = reader.u8();
+        cpu.rpl.copy_from_slice(reader.bytes(16));
+        cpu.operand = reader.u16();
+        cpu.vblank = reader.u8() != 0;
+        let (wait, x, key) = (reader.u8(), reader.u8(), reader.u8());
+        cpu.key_wait = match wait {
+            1 => Some(KeyWait::Press { x }),
+            2 => Some(KeyWait::Release { x, key }),
+            _ => None,
+        };
+
+        let ram = &mut self.bus.ram;
+        ram.memory.copy_from_slice(reader.bytes(RAM_SIZE));
+
+        let display = &mut self.bus.display;
+        let gfx_size = display.gfx.len();
+        display.gfx.copy_from_slice(reader.bytes(gfx_size));
+        display.hires = reader.u8() != 0;
+        display.planes = reader.u8() & (PLANES[0] | PLANES[1]);
+
+        let keys = (0..16).fold(0, |state, idx| state | ((reader.u8() != 0) as u16) << idx);
+        self.bus.keypad.restore(keys);
+
+        Ok(())
+    }
+}
+
+// Reads the fields of a state in order, the length of the data
+// is checked up front
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes(1)[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2));
+        u16::from_le_bytes(bytes)
+    }
+
+    fn u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8));
+        u64::from_le_bytes(bytes)
+    }
+}
@@ -0,0 +1,66 @@
+use chip8::audio::{Synth, Tone, PATTERN_SIZE};
+
+use ggez::{Context, GameError, GameResult};
+use rodio::{Sink, Source};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+// Streams the sound of the machine to the audio device, the emulator
+// updates the shared synth and the audio thread pulls samples from it
+pub struct SoundPlayer {
+    synth: Arc<Mutex<Synth>>,
+    _sink: Sink,
+}
+
+impl SoundPlayer {
+    pub fn new(ctx: &Context, tone: Tone) -> GameResult<SoundPlayer> {
+        let synth = Arc::new(Mutex::new(Synth::new(tone, SAMPLE_RATE)));
+        let sink =
+            Sink::try_new(ctx.audio.device()).map_err(|e| GameError::AudioError(e.to_string()))?;
+        sink.append(SynthSource {
+            synth: synth.clone(),
+        });
+
+        Ok(SoundPlayer { synth, _sink: sink })
+    }
+
+    pub fn update(&self, pattern: Option<[u8; PATTERN_SIZE]>, pitch: u8, playing: bool) {
+        let mut synth = self.synth.lock().unwrap();
+        synth.pattern = pattern;
+        synth.pitch = pitch;
+        synth.playing = playing;
+    }
+}
+
+// A never ending rodio source reading from the synth
+struct SynthSource {
+    synth: Arc<Mutex<Synth>>,
+}
+
+impl Iterator for SynthSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        Some(self.synth.lock().unwrap().next_sample())
+    }
+}
+
+impl Source for SynthSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.synth.lock().unwrap().sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
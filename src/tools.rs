@@ -0,0 +1,94 @@
+use chip8::asm;
+use chip8::disasm;
+
+use std::fs;
+use std::path::Path;
+
+// Subcommands that work on ROM files without opening a window. Returns the
+// exit code when the arguments name one of them
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.get(1)?;
+    let result = match command.as_str() {
+        "disasm" => disasm(&args[2..]),
+        "asm" => assemble(&args[2..]),
+        _ => return None,
+    };
+
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    })
+}
+
+pub fn print_usage(program: &str) {
+    eprintln!("       {} disasm <path_to_rom>", program);
+    eprintln!(
+        "       {} asm [-o <rom>] [--listing <file>] [--symbols <file>] [--define <name>] <source>",
+        program
+    );
+}
+
+fn disasm(args: &[String]) -> Result<(), String> {
+    let path = match args {
+        [path] => path,
+        _ => return Err("disasm needs exactly one ROM file".to_string()),
+    };
+    let rom = fs::read(path).map_err(|e| format!("could not read ROM file '{}': {}", path, e))?;
+
+    for line in disasm::disassemble(&rom) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn assemble(args: &[String]) -> Result<(), String> {
+    let mut source = None;
+    let mut output = None;
+    let mut listing = None;
+    let mut symbols = None;
+    let mut defines = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" => output = Some(iter.next().ok_or("-o needs a file")?.clone()),
+            "--listing" => listing = Some(iter.next().ok_or("--listing needs a file")?.clone()),
+            "--symbols" => symbols = Some(iter.next().ok_or("--symbols needs a file")?.clone()),
+            "--define" => defines.push(iter.next().ok_or("--define needs a name")?.as_str()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ if source.is_none() => source = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let source = source.ok_or("asm needs a source file")?;
+    let text =
+        fs::read(&source).map_err(|e| format!("could not read source file '{}': {}", source, e))?;
+    // The shipped sources are DOS text, anything that is not ASCII can
+    // only be in comments and strings
+    let text: String = text.iter().map(|b| *b as char).collect();
+    let assembly = asm::assemble(&text, &defines).map_err(|e| format!("{}: {}", source, e))?;
+
+    // The ROM goes next to the source unless told otherwise
+    let output = output.unwrap_or_else(|| {
+        Path::new(&source)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+    let write = |path: &str, data: &[u8]| {
+        fs::write(path, data).map_err(|e| format!("could not write '{}': {}", path, e))
+    };
+    write(&output, &assembly.binary)?;
+    if let Some(path) = listing {
+        write(&path, assembly.listing().as_bytes())?;
+    }
+    if let Some(path) = symbols {
+        write(&path, assembly.symbol_table().as_bytes())?;
+    }
+    println!("Assembled {} bytes into {}", assembly.binary.len(), output);
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use crate::cpu::CPU;
+use crate::error::Chip8Error;
+use crate::instruction::Instruction;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+// A log of every instruction the cpu completes, one line each with its
+// address, opcode, disassembly and the registers it changed:
+//
+//   0200  6A02  LD    VA, #02         VA=02
+//   0202  A2EA  LD    I, #2EA         I=02EA
+//   0204  DAB6  DRW   VA, VB, 6
+//
+// Nothing else goes into a line, no counters or timings, so that the traces
+// of two runs can be diffed to find the first instruction they disagree on.
+// Instructions that wait for a key or the display are only logged once they
+// complete, and the timers ticking between instructions is not a change.
+pub struct Trace {
+    out: Box<dyn Write>,
+    // Only instructions at these addresses are logged
+    range: RangeInclusive<u16>,
+    // Logging stops after this many lines
+    limit: Option<u64>,
+    lines: u64,
+    // The first write that failed, nothing is written after it
+    error: Option<io::Error>,
+}
+
+// The cpu as it was before an instruction ran
+pub struct Snapshot {
+    pc: u16,
+    opcode: u16,
+    next: u16,
+    gp: [u8; 16],
+    i: u16,
+    sp: u16,
+    dt: u8,
+    st: u8,
+}
+
+impl Snapshot {
+    pub fn new(cpu: &CPU, memory: &[u8]) -> Snapshot {
+        let word = |address: u16| {
+            let byte = |address: usize| memory.get(address).copied().unwrap_or(0);
+            (byte(address as usize) as u16) << 8 | byte(address as usize + 1) as u16
+        };
+        Snapshot {
+            pc: cpu.pc,
+            opcode: word(cpu.pc),
+            next: word(cpu.pc.wrapping_add(2)),
+            gp: cpu.gp,
+            i: cpu.i,
+            sp: cpu.sp,
+            dt: cpu.dt,
+            st: cpu.st,
+        }
+    }
+}
+
+impl Trace {
+    pub fn new(out: Box<dyn Write>) -> Trace {
+        Trace {
+            out,
+            range: 0..=0xFFFF,
+            limit: None,
+            lines: 0,
+            error: None,
+        }
+    }
+
+    pub fn set_range(&mut self, range: RangeInclusive<u16>) {
+        self.range = range;
+    }
+
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = Some(limit);
+    }
+
+    // Parses START-END, both hexadecimal and inclusive
+    pub fn parse_range(text: &str) -> Result<RangeInclusive<u16>, String> {
+        let invalid = || format!("invalid address range '{}', expected START-END", text);
+        let (start, end) = text.split_once('-').ok_or_else(invalid)?;
+        let address = |text: &str| u16::from_str_radix(text.trim_start_matches('#'), 16);
+        let start = address(start).map_err(|_| invalid())?;
+        let end = address(end).map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        Ok(start..=end)
+    }
+
+    // Whether the instruction at pc would be logged
+    pub fn wants(&self, pc: u16) -> bool {
+        self.error.is_none()
+            && self.range.contains(&pc)
+            && self.limit.is_none_or(|limit| self.lines < limit)
+    }
+
+    // Log an instruction that has completed
+    pub fn record(&mut self, before: &Snapshot, cpu: &CPU) {
+        let text = match Instruction::decode(before.opcode) {
+            Some(Instruction::LdLong) => format!("LD    I, LONG #{:04X}", before.next),
+            Some(instruction) => instruction.to_string(),
+            None => format!("DW    #{:04X}", before.opcode),
+        };
+
+        let mut changes = Vec::new();
+        for (idx, (old, new)) in before.gp.iter().zip(cpu.gp.iter()).enumerate() {
+            if old != new {
+                changes.push(format!("V{:X}={:02X}", idx, new));
+            }
+        }
+        if before.i != cpu.i {
+            changes.push(format!("I={:04X}", cpu.i));
+        }
+        if before.sp != cpu.sp {
+            changes.push(format!("SP={:X}", cpu.sp));
+        }
+        if before.dt != cpu.dt {
+            changes.push(format!("DT={:02X}", cpu.dt));
+        }
+        if before.st != cpu.st {
+            changes.push(format!("ST={:02X}", cpu.st));
+        }
+
+        let line = format!(
+            "{:04X}  {:04X}  {:<22}{}",
+            before.pc,
+            before.opcode,
+            text,
+            changes.join(" ")
+        );
+        self.write_line(line.trim_end());
+    }
+
+    // Log the instruction that stopped the machine
+    pub fn record_error(&mut self, before: &Snapshot, error: &Chip8Error) {
+        let line = format!("{:04X}  {:04X}  error: {}", before.pc, before.opcode, error);
+        self.write_line(&line);
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if let Err(e) = writeln!(self.out, "{}", line) {
+            self.error = Some(e);
+        }
+        self.lines += 1;
+    }
+
+    // Write out whatever is buffered, and report the first error
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()
+    }
+}
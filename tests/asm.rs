@@ -0,0 +1,89 @@
+use chip8::asm::assemble;
+use chip8::disasm::disassemble;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn games() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("games")
+}
+
+// The sources are DOS text with a few bytes that are not UTF-8
+fn read_source(name: &str) -> String {
+    let path = games().join("SOURCES").join(name).with_extension("SRC");
+    let bytes = fs::read(path).expect("could not read source");
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+fn assert_assembles_to_rom(name: &str) {
+    let assembly = assemble(&read_source(name), &[]).unwrap_or_else(|e| panic!("{}: {}", name, e));
+    let rom = fs::read(games().join(name).with_extension("ch8")).expect("could not read ROM");
+    assert!(
+        assembly.binary == rom,
+        "{} does not match the shipped ROM",
+        name
+    );
+}
+
+#[test]
+fn sources_assemble_to_the_shipped_roms() {
+    for name in &[
+        "15PUZZLE", "BLINKY", "BREAKOUT", "BRIX", "MAZE", "PONG", "PONG2", "SYZYGY", "UFO", "VBRIX",
+    ] {
+        assert_assembles_to_rom(name);
+    }
+}
+
+#[test]
+fn disassembly_assembles_back_to_the_rom() {
+    for name in &["PONG.ch8", "BRIX.ch8", "TETRIS.ch8", "TEST/IBM.ch8"] {
+        let rom = fs::read(games().join(name)).expect("could not read ROM");
+        let source: String = disassemble(&rom)
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let assembly = assemble(&format!("align off\n{}", source), &[])
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        assert!(
+            assembly.binary == rom,
+            "{} does not survive a round trip",
+            name
+        );
+    }
+}
+
+#[test]
+fn errors_have_line_numbers() {
+    let error = assemble("    CLS\n\n    JP Nowhere\n", &[]).err().unwrap();
+    assert_eq!(error.to_string(), "line 3: unknown symbol 'Nowhere'");
+
+    let error = assemble("    LD V0, 256\n", &[]).err().unwrap();
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn symbols_and_listing() {
+    let assembly = assemble("Size = 3\nStart: LD V0, Size\n    JP Start\n", &[]).unwrap();
+    assert_eq!(assembly.binary, vec![0x60, 0x03, 0x12, 0x00]);
+    assert_eq!(assembly.symbols["SIZE"], 3);
+    assert_eq!(assembly.symbols["START"], 0x200);
+    assert!(assembly.listing().contains("0202 1200"));
+}
+
+#[test]
+fn dividing_the_lowest_number_by_minus_one_wraps() {
+    let source = "Min = -9223372036854775807 - 1\n\
+                  Slash = Min / -1\n\
+                  Backslash = Min \\ -1\n\
+                  Rest = Min % -1\n\
+                  Negated = -Min\n\
+                  \x20   CLS\n";
+    let assembly = assemble(source, &[]).unwrap();
+    assert_eq!(assembly.symbols["SLASH"], i64::MIN);
+    assert_eq!(assembly.symbols["BACKSLASH"], i64::MIN);
+    assert_eq!(assembly.symbols["REST"], 0);
+    assert_eq!(assembly.symbols["NEGATED"], i64::MIN);
+
+    let error = assemble("X = 1 % 0\n", &[]).err().unwrap();
+    assert_eq!(error.to_string(), "line 1: division by zero");
+}
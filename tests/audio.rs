@@ -0,0 +1,105 @@
+use chip8::audio::{pattern_rate, Synth, Tone, Waveform, DEFAULT_PITCH, PATTERN_SIZE};
+use chip8::error::Chip8Error;
+use chip8::quirks::Quirks;
+use chip8::Chip8;
+
+fn run(rom: &[u8], steps: usize) -> Result<Chip8, Chip8Error> {
+    let mut chip8 = Chip8::new(Quirks::default());
+    chip8.load_rom(rom)?;
+    for _ in 0..steps {
+        chip8.step()?;
+    }
+    Ok(chip8)
+}
+
+#[test]
+fn audio_loads_the_pattern_at_i() {
+    // LD I, #208; AUDIO; JP #204, followed by the pattern
+    let mut rom = vec![0xA2, 0x08, 0xF0, 0x02, 0x12, 0x04, 0x00, 0x00];
+    let pattern: Vec<u8> = (0..PATTERN_SIZE as u8).map(|n| n * 17).collect();
+    rom.extend(&pattern);
+
+    let chip8 = run(&rom, 1).unwrap();
+    assert_eq!(chip8.cpu.audio_pattern, None);
+    let chip8 = run(&rom, 2).unwrap();
+    assert_eq!(chip8.cpu.audio_pattern.unwrap()[..], pattern[..]);
+    assert_eq!(chip8.cpu.i, 0x208);
+
+    // A pattern that would run past the end of memory
+    let mut chip8 = run(&[0xF0, 0x02], 0).unwrap();
+    chip8.cpu.i = 0xFFF8;
+    assert!(matches!(
+        chip8.step(),
+        Err(Chip8Error::MemoryOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn pitch_sets_the_pattern_rate() {
+    // LD V3, #70; PITCH V3
+    let chip8 = run(&[0x63, 0x70, 0xF3, 0x3A], 1).unwrap();
+    assert_eq!(chip8.cpu.pitch, DEFAULT_PITCH);
+    let chip8 = run(&[0x63, 0x70, 0xF3, 0x3A], 2).unwrap();
+    assert_eq!(chip8.cpu.pitch, 0x70);
+
+    // Every 48 steps of pitch is an octave
+    assert_eq!(pattern_rate(64), 4000.0);
+    assert_eq!(pattern_rate(112), 8000.0);
+    assert_eq!(pattern_rate(16), 2000.0);
+    assert!(pattern_rate(65) > 4000.0);
+}
+
+#[test]
+fn synth_is_silent_until_playing() {
+    let mut synth = Synth::new(Tone::default(), 8000);
+    assert!((0..100).all(|_| synth.next_sample() == 0.0));
+}
+
+#[test]
+fn synth_plays_the_tone_at_its_volume() {
+    // A 1000 Hz square wave sampled at 8000 Hz is high for four samples,
+    // then low for four
+    let tone = Tone {
+        frequency: 1000.0,
+        waveform: Waveform::Square,
+        volume: 0.5,
+    };
+    let mut synth = Synth::new(tone, 8000);
+    synth.playing = true;
+    let samples: Vec<f32> = (0..16).map(|_| synth.next_sample()).collect();
+    assert_eq!(samples[..8], [0.5, 0.5, 0.5, 0.5, -0.5, -0.5, -0.5, -0.5]);
+    assert_eq!(samples[8..], samples[..8]);
+
+    // Stopping and starting again begins the wave from the start
+    synth.next_sample();
+    synth.playing = false;
+    assert_eq!(synth.next_sample(), 0.0);
+    synth.playing = true;
+    assert_eq!(synth.next_sample(), 0.5);
+}
+
+#[test]
+fn synth_plays_the_pattern_instead_of_the_tone() {
+    // At the default pitch the 128 bits play at 4000 per second, so at
+    // 8000 Hz every bit lasts two samples
+    let mut pattern = [0; PATTERN_SIZE];
+    pattern[0] = 0b1010_0000;
+    let mut synth = Synth::new(Tone::default(), 8000);
+    synth.pattern = Some(pattern);
+    synth.playing = true;
+    let samples: Vec<f32> = (0..8).map(|_| synth.next_sample()).collect();
+    assert_eq!(
+        samples,
+        [0.25, 0.25, -0.25, -0.25, 0.25, 0.25, -0.25, -0.25]
+    );
+}
+
+#[test]
+fn waveforms_by_name() {
+    assert_eq!("square".parse(), Ok(Waveform::Square));
+    assert_eq!("Triangle".parse(), Ok(Waveform::Triangle));
+    assert_eq!("saw".parse(), Ok(Waveform::Sawtooth));
+    assert_eq!("SAWTOOTH".parse(), Ok(Waveform::Sawtooth));
+    assert_eq!("sine".parse(), Ok(Waveform::Sine));
+    assert!("noise".parse::<Waveform>().is_err());
+}
@@ -0,0 +1,83 @@
+use chip8::bindings::Bindings;
+
+#[test]
+fn layouts() {
+    let qwerty = Bindings::default();
+    assert_eq!(qwerty.key("Key4"), Some(0xC));
+    assert_eq!(qwerty.key("x"), Some(0x0));
+    assert_eq!(qwerty.key("V"), Some(0xF));
+    // Keys outside the layout press nothing, not key F
+    assert_eq!(qwerty.key("Space"), None);
+    assert_eq!(qwerty.key("Up"), None);
+
+    let azerty = Bindings::layout("AZERTY").unwrap();
+    assert_eq!(azerty.key("A"), Some(0x4));
+    assert_eq!(azerty.key("Q"), Some(0x7));
+    assert_eq!(azerty.key("W"), Some(0xA));
+
+    let numpad = Bindings::layout("numpad").unwrap();
+    assert_eq!(numpad.key("Numpad7"), Some(0x7));
+    assert_eq!(numpad.key("NumpadAdd"), Some(0xF));
+    assert_eq!(numpad.key("Key7"), None);
+
+    assert!(Bindings::layout("dvorak").is_err());
+}
+
+#[test]
+fn files_rebind_the_keys_they_list() {
+    let bindings = Bindings::parse(
+        r#"
+        # PONG with the arrows as well
+        [keys]
+        1 = ["Key1", "Up"]
+        4 = [ "Q", "Down" ]
+        F = []
+        layout = "azerty"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(bindings.key("Up"), Some(0x1));
+    assert_eq!(bindings.key("Key1"), Some(0x1));
+    assert_eq!(bindings.key("Down"), Some(0x4));
+    assert_eq!(bindings.key("Q"), Some(0x4));
+    // A no longer presses 4, which it did in the layout
+    assert_eq!(bindings.key("A"), None);
+    assert_eq!(bindings.key("V"), None);
+    assert_eq!(bindings.key("Z"), Some(0x5));
+
+    assert_eq!(
+        Bindings::parse("[keys]\n10 = \"A\"").unwrap_err(),
+        "line 2: '10' is not a key from 0 to F"
+    );
+    assert!(Bindings::parse("1 = Key1").is_err());
+    assert!(Bindings::parse("1 = [\"Key1\"").is_err());
+}
+
+#[test]
+fn gamepads() {
+    let bindings = Bindings::default();
+    assert_eq!(bindings.pad_key(1, "DPadUp"), Some(0x2));
+    assert_eq!(bindings.pad_key(2, "leftsticky-"), Some(0x8));
+    assert_eq!(bindings.pad_key(1, "Start"), None);
+
+    // PONG2 puts the second player on the keys of the right paddle
+    let text = std::fs::read_to_string("games/PONG2.bindings").unwrap();
+    let pong2 = Bindings::parse(&text).unwrap();
+    assert_eq!(pong2.pad_key(1, "DPadUp"), Some(0x1));
+    assert_eq!(pong2.pad_key(1, "LeftStickY-"), Some(0x4));
+    assert_eq!(pong2.pad_key(2, "DPadUp"), Some(0xC));
+    assert_eq!(pong2.pad_key(2, "DPadDown"), Some(0xD));
+    // Player sections start out empty, other pads keep the defaults
+    assert_eq!(pong2.pad_key(1, "DPadLeft"), None);
+    assert_eq!(pong2.pad_key(3, "DPadLeft"), Some(0x4));
+    assert_eq!(pong2.key("Key1"), Some(0x1));
+
+    let mut bindings = Bindings::layout("numpad").unwrap();
+    bindings.update("[pad]\n5 = \"East\"").unwrap();
+    assert_eq!(bindings.pad_key(1, "East"), Some(0x5));
+    assert_eq!(bindings.pad_key(1, "South"), None);
+    assert_eq!(bindings.key("Numpad5"), Some(0x5));
+
+    assert!(Bindings::parse("[pad0]").is_err());
+    assert!(Bindings::parse("[mouse]").is_err());
+}
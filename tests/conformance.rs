@@ -0,0 +1,99 @@
+// Boots each ROM in games/TEST for a fixed number of frames and compares
+// the screen with a golden image in tests/golden. After a deliberate change
+// to what a ROM draws, write the new images with
+//
+//   BLESS=1 cargo test --test conformance
+//
+// and review the diff of tests/golden before committing it.
+use chip8::cpu::StepOutcome;
+use chip8::Chip8;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// The random numbers are fixed so that the screens are too
+const SEED: u64 = 0;
+
+fn run_rom(rom: &str, frames: u32) -> Chip8 {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let data = fs::read(root.join("games/TEST").join(rom)).expect("could not read test ROM");
+
+    let mut chip8 = Chip8::default();
+    chip8.cpu.seed_rng(SEED);
+    chip8.load_rom(&data).expect("could not load test ROM");
+
+    for frame in 0..frames {
+        match chip8.run_frame() {
+            Ok(StepOutcome::Exited) => break,
+            Ok(_) => {}
+            Err(e) => panic!("{} halted on frame {}: {}", rom, frame, e),
+        }
+    }
+    chip8
+}
+
+fn check_golden(rom: &str, frames: u32) {
+    let screen = run_rom(rom, frames).bus.display.to_ascii();
+    let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(rom)
+        .with_extension("txt");
+
+    if env::var_os("BLESS").is_some() {
+        fs::write(&golden, &screen).expect("could not write golden image");
+        return;
+    }
+
+    let expected = fs::read_to_string(&golden).unwrap_or_else(|_| {
+        panic!(
+            "no golden image {}, create it with BLESS=1",
+            golden.display()
+        )
+    });
+    if screen != expected {
+        let row = screen
+            .lines()
+            .zip(expected.lines())
+            .position(|(found, expected)| found != expected)
+            .unwrap_or(0);
+        panic!(
+            "{} differs from {} from row {} on\n\nexpected:\n{}\nfound:\n{}",
+            rom,
+            golden.display(),
+            row,
+            expected,
+            screen
+        );
+    }
+}
+
+#[test]
+fn ibm_logo() {
+    check_golden("IBM.ch8", 60);
+}
+
+#[test]
+fn c8pic() {
+    check_golden("C8PIC.ch8", 120);
+}
+
+#[test]
+fn x_mirror() {
+    check_golden("X-MIRROR.ch8", 300);
+}
+
+#[test]
+fn timebomb() {
+    check_golden("TIMEBOMB.ch8", 120);
+}
+
+#[test]
+fn rocket2() {
+    check_golden("Rocket2.ch8", 120);
+}
+
+#[test]
+fn tapeworm() {
+    check_golden("TAPEWORM.ch8", 120);
+}
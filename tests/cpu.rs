@@ -0,0 +1,370 @@
+use chip8::cpu::StepOutcome;
+use chip8::error::Chip8Error;
+use chip8::quirks::Quirks;
+use chip8::Chip8;
+
+// A machine that has run the first instructions of a ROM
+fn run(quirks: Quirks, rom: &[u8], steps: usize) -> Result<Chip8, Chip8Error> {
+    let mut chip8 = Chip8::new(quirks);
+    chip8.load_rom(rom)?;
+    for _ in 0..steps {
+        chip8.step()?;
+    }
+    Ok(chip8)
+}
+
+// A machine with the given words at the very end of memory, where pc is
+fn at_end_of_memory(quirks: Quirks, opcode: u16) -> Chip8 {
+    let mut chip8 = Chip8::new(quirks);
+    chip8.bus.ram.memory[0xFFFE] = (opcode >> 8) as u8;
+    chip8.bus.ram.memory[0xFFFF] = opcode as u8;
+    chip8.cpu.pc = 0xFFFE;
+    chip8
+}
+
+#[test]
+fn instructions_that_stay_put_at_the_end_of_memory() {
+    let mut chip8 = at_end_of_memory(Quirks::default(), 0x00FD);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Exited);
+    assert_eq!(chip8.cpu.pc, 0xFFFE);
+
+    let mut chip8 = at_end_of_memory(Quirks::cosmac_vip(), 0xD011);
+    chip8.cpu.vblank = false;
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForDisplay);
+    assert_eq!(chip8.cpu.pc, 0xFFFE);
+
+    let mut chip8 = at_end_of_memory(Quirks::default(), 0xF30A);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+    assert_eq!(chip8.cpu.pc, 0xFFFE);
+    chip8.bus.keypad.press_key(0x2);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    assert_eq!(chip8.cpu.pc, 0x0000);
+}
+
+#[test]
+fn errors_stop_the_machine_instead_of_panicking() {
+    assert_eq!(
+        run(Quirks::default(), &[0x50, 0x01], 1).err(),
+        Some(Chip8Error::UnknownOpcode {
+            pc: 0x200,
+            opcode: 0x5001
+        })
+    );
+    // RET with an empty stack
+    assert_eq!(
+        run(Quirks::default(), &[0x00, 0xEE], 1).err(),
+        Some(Chip8Error::StackUnderflow { pc: 0x200 })
+    );
+    // CALL #200 calls itself until the 16 entries are used up
+    assert_eq!(
+        run(Quirks::default(), &[0x22, 0x00], 17).err(),
+        Some(Chip8Error::StackOverflow { pc: 0x200 })
+    );
+    // LD [I], V1 with I on the last byte of memory writes past it
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&[0xF1, 0x55]).unwrap();
+    chip8.cpu.i = 0xFFFF;
+    assert_eq!(
+        chip8.step(),
+        Err(Chip8Error::MemoryOutOfBounds { addr: 0x10000 })
+    );
+
+    let mut chip8 = Chip8::default();
+    assert_eq!(
+        chip8.load_rom(&vec![0; 0x10000]),
+        Err(Chip8Error::RomTooLarge { size: 0x10000 })
+    );
+}
+
+// The default quirks with one of them set
+fn quirk(name: &str, value: bool) -> Quirks {
+    let mut quirks = Quirks::default();
+    quirks.set(name, value).unwrap();
+    quirks
+}
+
+#[test]
+fn quirks_change_the_ambiguous_instructions() {
+    // LD V0, #03; LD V1, #0C; SHR V0, V1
+    let rom = [0x60, 0x03, 0x61, 0x0C, 0x80, 0x16];
+    let chip8 = run(quirk("shift", true), &rom, 3).unwrap();
+    assert_eq!((chip8.cpu.gp[0], chip8.cpu.gp[0xF]), (0x01, 1));
+    let chip8 = run(quirk("shift", false), &rom, 3).unwrap();
+    assert_eq!((chip8.cpu.gp[0], chip8.cpu.gp[0xF]), (0x06, 0));
+
+    // LD I, #300; LD [I], V2
+    let rom = [0xA3, 0x00, 0xF2, 0x55];
+    assert_eq!(
+        run(quirk("load_store", true), &rom, 2).unwrap().cpu.i,
+        0x303
+    );
+    assert_eq!(
+        run(quirk("load_store", false), &rom, 2).unwrap().cpu.i,
+        0x300
+    );
+
+    // LD V1, #10; LD V0, #20; JP V0, #100
+    let rom = [0x61, 0x10, 0x60, 0x20, 0xB1, 0x00];
+    assert_eq!(run(quirk("jump", true), &rom, 3).unwrap().cpu.pc, 0x110);
+    assert_eq!(run(quirk("jump", false), &rom, 3).unwrap().cpu.pc, 0x120);
+
+    // LD VF, #05; OR V0, V1
+    let rom = [0x6F, 0x05, 0x80, 0x11];
+    assert_eq!(
+        run(quirk("vf_reset", true), &rom, 2).unwrap().cpu.gp[0xF],
+        0
+    );
+    assert_eq!(
+        run(quirk("vf_reset", false), &rom, 2).unwrap().cpu.gp[0xF],
+        5
+    );
+
+    // LD V0, #3E; LD I, #000; DRW V0, V1, 1 draws the top of the 0 in the
+    // font, which is four pixels wide, over the right edge
+    let rom = [0x60, 0x3E, 0xA0, 0x00, 0xD0, 0x11];
+    let chip8 = run(quirk("clipping", true), &rom, 3).unwrap();
+    assert_eq!(chip8.bus.display.get_pixel(0, 0), 0);
+    let chip8 = run(quirk("clipping", false), &rom, 3).unwrap();
+    assert_eq!(chip8.bus.display.get_pixel(0, 0), 1);
+
+    // DRW V0, V0, 1 twice
+    let rom = [0xD0, 0x01, 0xD0, 0x01];
+    let mut chip8 = run(quirk("display_wait", true), &rom, 1).unwrap();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForDisplay);
+    chip8.end_frame();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    let mut chip8 = run(quirk("display_wait", false), &rom, 1).unwrap();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+
+    assert!(Quirks::default().set("wobble", true).is_err());
+}
+
+#[test]
+fn the_default_quirks_keep_the_old_behaviour() {
+    assert_eq!(Quirks::default(), Quirks::legacy());
+    assert_eq!("legacy".parse(), Ok(Quirks::legacy()));
+
+    // LD V0, #10; LD V1, #20; JP V0, #120 jumps from V0
+    let chip8 = run(Quirks::default(), &[0x60, 0x10, 0x61, 0x20, 0xB1, 0x20], 3).unwrap();
+    assert_eq!(chip8.cpu.pc, 0x130);
+
+    // LD V0, #3E; LD I, #000; DRW V0, V1, 1 wraps the 0 around
+    let chip8 = run(Quirks::default(), &[0x60, 0x3E, 0xA0, 0x00, 0xD0, 0x11], 3).unwrap();
+    assert_eq!(chip8.bus.display.get_pixel(0, 0), 1);
+}
+
+#[test]
+fn super_chip_only_waits_for_the_display_in_low_resolution() {
+    assert_ne!(Quirks::super_chip(), Quirks::chip48());
+
+    // DRW V0, V0, 1 twice, then the same in high resolution after HIGH
+    let rom = [0xD0, 0x01, 0xD0, 0x01];
+    let mut chip8 = run(Quirks::super_chip(), &rom, 1).unwrap();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForDisplay);
+
+    let rom = [0x00, 0xFF, 0xD0, 0x01, 0xD0, 0x01];
+    let mut chip8 = run(Quirks::super_chip(), &rom, 2).unwrap();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+}
+
+#[test]
+fn super_chip_instructions() {
+    // HIGH; LD I, #220; DRW V0, V0, 0; SCD 2; SCR; SCL; LOW; EXIT, with a
+    // filled 16x16 sprite at #220
+    let mut rom = vec![
+        0x00, 0xFF, 0xA2, 0x20, 0xD0, 0x00, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC, 0x00, 0xFE, 0x00,
+        0xFD,
+    ];
+    rom.resize(0x20, 0);
+    rom.extend([0xFF; 32]);
+
+    let mut chip8 = run(Quirks::super_chip(), &rom, 1).unwrap();
+    assert!(chip8.bus.display.hires);
+    assert_eq!(chip8.bus.display.width(), 128);
+    assert_eq!(chip8.bus.display.height(), 64);
+
+    chip8.step().unwrap();
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(15, 15), 1);
+    assert_eq!(display.get_pixel(16, 0), 0);
+    assert_eq!(display.get_pixel(0, 16), 0);
+
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(0, 1), 0);
+    assert_eq!(display.get_pixel(0, 2), 1);
+    assert_eq!(display.get_pixel(0, 17), 1);
+
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(3, 2), 0);
+    assert_eq!(display.get_pixel(4, 2), 1);
+    assert_eq!(display.get_pixel(19, 2), 1);
+    assert_eq!(display.get_pixel(20, 2), 0);
+
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(0, 2), 1);
+    assert_eq!(display.get_pixel(15, 2), 1);
+    assert_eq!(display.get_pixel(16, 2), 0);
+
+    chip8.step().unwrap();
+    assert!(!chip8.bus.display.hires);
+    assert_eq!(chip8.bus.display.width(), 64);
+
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Exited);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Exited);
+    assert_eq!(chip8.cpu.pc, 0x20E);
+
+    // LD V1, #07; LD HF, V1 points I at the large 7
+    let chip8 = run(Quirks::super_chip(), &[0x61, 0x07, 0xF1, 0x30], 2).unwrap();
+    assert_eq!(chip8.cpu.i, 0x050 + 7 * 10);
+
+    // LD V0, #11; LD V1, #22; LD R, V1; LD V0, #00; LD V1, #00; LD V1, R
+    let rom = [
+        0x60, 0x11, 0x61, 0x22, 0xF1, 0x75, 0x60, 0x00, 0x61, 0x00, 0xF1, 0x85,
+    ];
+    let chip8 = run(Quirks::super_chip(), &rom, 5).unwrap();
+    assert_eq!(chip8.cpu.rpl[..3], [0x11, 0x22, 0x00]);
+    assert_eq!(chip8.cpu.gp[..2], [0x00, 0x00]);
+    let chip8 = run(Quirks::super_chip(), &rom, 6).unwrap();
+    assert_eq!(chip8.cpu.gp[..2], [0x11, 0x22]);
+}
+
+#[test]
+fn xo_chip_long_load_and_skips() {
+    // LD I, #1234 takes two words
+    let chip8 = run(Quirks::default(), &[0xF0, 0x00, 0x12, 0x34], 1).unwrap();
+    assert_eq!(chip8.cpu.i, 0x1234);
+    assert_eq!(chip8.cpu.pc, 0x204);
+
+    // SE V0, #00 skips both words of LD I, #1234 and lands on LD V1, #01
+    let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01];
+    let chip8 = run(Quirks::default(), &rom, 1).unwrap();
+    assert_eq!(chip8.cpu.pc, 0x206);
+    let chip8 = run(Quirks::default(), &rom, 2).unwrap();
+    assert_eq!(chip8.cpu.gp[1], 0x01);
+    assert_eq!(chip8.cpu.i, 0);
+
+    // SNE V0, #00 does not skip, so LD I, #1234 runs
+    let rom = [0x40, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01];
+    let chip8 = run(Quirks::default(), &rom, 2).unwrap();
+    assert_eq!(chip8.cpu.i, 0x1234);
+    assert_eq!(chip8.cpu.pc, 0x206);
+}
+
+#[test]
+fn xo_chip_register_ranges() {
+    // LD V1, #11; LD V2, #22; LD V3, #33; LD I, #300; SAVE V1, V3;
+    // LD I, #310; SAVE V3, V1
+    let rom = [
+        0x61, 0x11, 0x62, 0x22, 0x63, 0x33, 0xA3, 0x00, 0x51, 0x32, 0xA3, 0x10, 0x53, 0x12,
+    ];
+    let chip8 = run(Quirks::default(), &rom, 7).unwrap();
+    let memory = &chip8.bus.ram.memory;
+    assert_eq!(memory[0x300..0x304], [0x11, 0x22, 0x33, 0x00]);
+    assert_eq!(memory[0x310..0x314], [0x33, 0x22, 0x11, 0x00]);
+    // I stays where it was
+    assert_eq!(chip8.cpu.i, 0x310);
+
+    // LD I, #300; LOAD V4, V6; LOAD V9, V7
+    let rom = [0xA3, 0x00, 0x54, 0x63, 0x59, 0x73];
+    let mut chip8 = run(Quirks::default(), &rom, 0).unwrap();
+    chip8.bus.ram.memory[0x300..0x303].copy_from_slice(&[0xAA, 0xBB, 0xCC]);
+    for _ in 0..3 {
+        chip8.step().unwrap();
+    }
+    assert_eq!(chip8.cpu.gp[4..10], [0xAA, 0xBB, 0xCC, 0xCC, 0xBB, 0xAA]);
+    assert_eq!(chip8.cpu.i, 0x300);
+}
+
+#[test]
+fn xo_chip_planes_and_scroll_up() {
+    // PLANE 3; LD I, #20C; DRW V0, V0, 1; PLANE 2; DRW V1, V1, 1; SCU 3
+    // with the rows of the two planes at #20C
+    let rom = [
+        0xF3, 0x01, 0xA2, 0x0C, 0xD0, 0x01, 0xF2, 0x01, 0xD1, 0x11, 0x00, 0xD3, 0xF0, 0x3C,
+    ];
+    let mut chip8 = run(Quirks::default(), &rom, 3).unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(0, 0), 0b01);
+    assert_eq!(display.get_pixel(2, 0), 0b11);
+    assert_eq!(display.get_pixel(4, 0), 0b10);
+    assert_eq!(display.get_pixel(6, 0), 0b00);
+    assert_eq!(chip8.cpu.gp[0xF], 0);
+
+    // Only the second plane takes the first row of the sprite, V1 is 5
+    chip8.cpu.gp[1] = 5;
+    chip8.step().unwrap();
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(5, 5), 0b10);
+    assert_eq!(display.get_pixel(9, 5), 0b00);
+    assert_eq!(display.get_pixel(2, 0), 0b11);
+
+    // Scrolling only moves the selected planes
+    chip8.step().unwrap();
+    let display = &chip8.bus.display;
+    assert_eq!(display.get_pixel(5, 2), 0b10);
+    assert_eq!(display.get_pixel(5, 5), 0b00);
+    assert_eq!(display.get_pixel(2, 0), 0b01);
+}
+
+#[test]
+fn xo_chip_addresses_all_64_kib() {
+    // LD I, #8000; LD V0, #5A; LD V1, #A5; LD [I], V1; LD I, #8000; LD V1, [I]
+    let rom = [
+        0xF0, 0x00, 0x80, 0x00, 0x60, 0x5A, 0x61, 0xA5, 0xF1, 0x55, 0xF0, 0x00, 0x80, 0x00, 0x60,
+        0x00, 0x61, 0x00, 0xF1, 0x65,
+    ];
+    let chip8 = run(Quirks::default(), &rom, 4).unwrap();
+    assert_eq!(chip8.bus.ram.memory[0x8000..0x8002], [0x5A, 0xA5]);
+    let chip8 = run(Quirks::default(), &rom, 8).unwrap();
+    assert_eq!(chip8.cpu.gp[..2], [0x5A, 0xA5]);
+
+    // LD I, #FFF; LD V0, #01; ADD I, V0 goes past the 12 bits of NNN
+    let chip8 = run(Quirks::default(), &[0xAF, 0xFF, 0x60, 0x01, 0xF0, 0x1E], 3).unwrap();
+    assert_eq!(chip8.cpu.i, 0x1000);
+
+    // LD I, #FFFF; LD V0, #02; ADD I, V0 wraps around to the start
+    let rom = [0xF0, 0x00, 0xFF, 0xFF, 0x60, 0x02, 0xF0, 0x1E];
+    let chip8 = run(Quirks::default(), &rom, 3).unwrap();
+    assert_eq!(chip8.cpu.i, 0x0001);
+}
+
+// The numbers RND V0, #FF produces, one for each time round the loop
+fn random_bytes(seed: u64, count: usize) -> Vec<u8> {
+    // RND V0, #FF; JP #200
+    let mut chip8 = run(Quirks::default(), &[0xC0, 0xFF, 0x12, 0x00], 0).unwrap();
+    chip8.cpu.seed_rng(seed);
+    (0..count)
+        .map(|_| {
+            chip8.step().unwrap();
+            chip8.step().unwrap();
+            chip8.cpu.gp[0]
+        })
+        .collect()
+}
+
+#[test]
+fn seeded_random_numbers_repeat() {
+    assert_eq!(random_bytes(7, 64), random_bytes(7, 64));
+    assert_ne!(random_bytes(7, 64), random_bytes(8, 64));
+
+    // Every byte turns up sooner or later
+    let mut seen = [false; 256];
+    for byte in random_bytes(7, 10_000) {
+        seen[byte as usize] = true;
+    }
+    assert!(seen.iter().all(|seen| *seen));
+
+    // RND V0, #0F only keeps the low bits
+    let mut chip8 = run(Quirks::default(), &[0xC0, 0x0F, 0x12, 0x00], 0).unwrap();
+    chip8.cpu.seed_rng(7);
+    for _ in 0..100 {
+        chip8.step().unwrap();
+        assert_eq!(chip8.cpu.gp[0] & 0xF0, 0);
+        chip8.step().unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use chip8::debugger::Debugger;
+use chip8::Chip8;
+
+// LD V0, #05; ADD V0, #01; JP #202
+const LOOP: [u8; 6] = [0x60, 0x05, 0x70, 0x01, 0x12, 0x02];
+
+fn machine() -> (Chip8, Debugger) {
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&LOOP).expect("could not load ROM");
+    (chip8, Debugger::new())
+}
+
+fn run(debugger: &mut Debugger, chip8: &mut Chip8, line: &str) -> String {
+    debugger.execute(chip8, line).expect("machine error");
+    debugger.take_output()
+}
+
+#[test]
+fn step_shows_the_next_instruction() {
+    let (mut chip8, mut debugger) = machine();
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "step"),
+        ">  0202  7001  ADD   V0, #01\n"
+    );
+    assert_eq!(chip8.cpu.gp[0], 5);
+
+    // An empty line repeats the last command
+    run(&mut debugger, &mut chip8, "");
+    assert_eq!(chip8.cpu.gp[0], 6);
+    run(&mut debugger, &mut chip8, "s 3");
+    assert_eq!(chip8.cpu.gp[0], 7);
+    assert_eq!(chip8.cpu.pc, 0x202);
+}
+
+#[test]
+fn breakpoints_stop_in_the_middle_of_a_frame() {
+    let (mut chip8, mut debugger) = machine();
+    run(&mut debugger, &mut chip8, "break 204");
+    run(&mut debugger, &mut chip8, "continue");
+    assert!(!debugger.paused);
+
+    debugger.run_frame(&mut chip8).unwrap();
+    assert!(debugger.paused);
+    assert!(!debugger.at_frame_start());
+    assert_eq!(chip8.cpu.pc, 0x204);
+    assert_eq!(
+        debugger.take_output(),
+        "Breakpoint 0 at 0204\n>* 0204  1202  JP    #202\n"
+    );
+
+    // Continuing runs past the breakpoint it stopped at
+    run(&mut debugger, &mut chip8, "delete 0");
+    run(&mut debugger, &mut chip8, "breakop 7??1");
+    run(&mut debugger, &mut chip8, "c");
+    debugger.run_frame(&mut chip8).unwrap();
+    assert_eq!(chip8.cpu.pc, 0x202);
+    assert!(debugger
+        .take_output()
+        .ends_with("Breakpoint 0 on 7??1\n>  0202  7001  ADD   V0, #01\n"));
+}
+
+#[test]
+fn memory_and_registers_can_be_changed() {
+    let (mut chip8, mut debugger) = machine();
+    run(&mut debugger, &mut chip8, "poke 300 AB #CD");
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "x 2FF 3"),
+        "02FF: 00 AB CD\n"
+    );
+
+    run(&mut debugger, &mut chip8, "set vA 42");
+    run(&mut debugger, &mut chip8, "set I 0x300");
+    run(&mut debugger, &mut chip8, "set dt 3C");
+    let registers = run(&mut debugger, &mut chip8, "regs");
+    assert!(registers.starts_with("pc: 0200 i: 0300 sp: 0 dt: 3C st: 00\n"));
+    assert!(registers.contains("VA: 42"));
+
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "set V0 100"),
+        "Error: 100 does not fit in V0\n"
+    );
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "jump"),
+        "Error: unknown command 'jump', type help for a list\n"
+    );
+}
+
+#[test]
+fn list_disassembles_around_pc() {
+    let (mut chip8, mut debugger) = machine();
+    run(&mut debugger, &mut chip8, "step 2");
+    run(&mut debugger, &mut chip8, "b 202");
+    let lines: Vec<String> = run(&mut debugger, &mut chip8, "list")
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(lines.len(), 10);
+    assert_eq!(lines[2], "   0200  6005  LD    V0, #05");
+    assert_eq!(lines[3], " * 0202  7001  ADD   V0, #01");
+    assert_eq!(lines[4], ">  0204  1202  JP    #202");
+    assert_eq!(lines[5], "   0206  0000  DW    #0000");
+}
+
+#[test]
+fn setting_a_breakpoint_twice_keeps_one() {
+    let (mut chip8, mut debugger) = machine();
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "break 202"),
+        "Breakpoint 0 at 0202\n"
+    );
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "breakop 7??1"),
+        "Breakpoint 1 on 7??1\n"
+    );
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "b 0x202"),
+        "Breakpoint 0 at 0202 is already set\n"
+    );
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "breakop 7??1"),
+        "Breakpoint 1 on 7??1 is already set\n"
+    );
+    run(&mut debugger, &mut chip8, "delete 0");
+    run(&mut debugger, &mut chip8, "delete 0");
+    assert_eq!(
+        run(&mut debugger, &mut chip8, "delete 0"),
+        "Error: there is no breakpoint 0\n"
+    );
+}
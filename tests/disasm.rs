@@ -0,0 +1,33 @@
+use chip8::disasm::disassemble;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn disassemble_game(name: &str) -> Vec<String> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("games")
+        .join(name);
+    let rom = fs::read(path).expect("could not read ROM");
+    disassemble(&rom)
+        .iter()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+#[test]
+fn pong_code_and_labels() {
+    let lines = disassemble_game("PONG.ch8");
+    assert_eq!(lines[0], "L200:\n    LD    VA, #02         ; 0200  6A02");
+    assert_eq!(lines[4], "    LD    I, D2EA         ; 0208  A2EA");
+    assert_eq!(lines[8], "    CALL  L2D4            ; 0210  22D4");
+}
+
+#[test]
+fn pong_sprites_are_data() {
+    let lines = disassemble_game("PONG.ch8");
+    let paddle = lines
+        .iter()
+        .find(|line| line.starts_with("D2EA:"))
+        .expect("no label for the paddle sprite");
+    assert_eq!(paddle, "D2EA:\n    DB    #80             ; 02EA  #.......");
+}
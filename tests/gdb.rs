@@ -0,0 +1,135 @@
+use chip8::debugger::Debugger;
+use chip8::gdb::GdbStub;
+use chip8::Chip8;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+// LD V0, #05; ADD V0, #01; JP #202
+const LOOP: [u8; 6] = [0x60, 0x05, 0x70, 0x01, 0x12, 0x02];
+
+// A stub with a client connected to it over a real socket
+struct Session {
+    stub: GdbStub,
+    debugger: Debugger,
+    chip8: Chip8,
+    client: TcpStream,
+    received: Vec<u8>,
+}
+
+impl Session {
+    fn new() -> Session {
+        let stub = GdbStub::bind("127.0.0.1:0").expect("could not listen");
+        let client = TcpStream::connect(stub.local_addr().unwrap()).expect("could not connect");
+        client
+            .set_read_timeout(Some(Duration::from_millis(5)))
+            .unwrap();
+        let mut chip8 = Chip8::default();
+        chip8.load_rom(&LOOP).unwrap();
+        Session {
+            stub,
+            debugger: Debugger::new(),
+            chip8,
+            client,
+            received: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, packet: &str) {
+        let checksum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.client, "${}#{:02x}", packet, checksum).unwrap();
+    }
+
+    // Keep the stub and the machine going until a reply arrives
+    fn reply(&mut self) -> String {
+        for _ in 0..400 {
+            self.stub
+                .poll(&mut self.debugger, &mut self.chip8)
+                .expect("machine error");
+            if !self.debugger.paused {
+                self.debugger.run_frame(&mut self.chip8).unwrap();
+            }
+
+            let mut bytes = [0; 256];
+            match self.client.read(&mut bytes) {
+                Ok(count) => self.received.extend_from_slice(&bytes[..count]),
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == ErrorKind::TimedOut => {}
+                Err(e) => panic!("connection lost: {}", e),
+            }
+
+            // Skip the acknowledgements in front of the packet
+            while self.received.first() == Some(&b'+') {
+                self.received.remove(0);
+            }
+            if let Some(end) = self.received.iter().position(|&b| b == b'#') {
+                if self.received.len() >= end + 3 {
+                    let packet: Vec<u8> = self.received.drain(..end + 3).collect();
+                    assert_eq!(packet[0], b'$');
+                    return String::from_utf8(packet[1..end].to_vec()).unwrap();
+                }
+            }
+        }
+        panic!("no reply from the stub");
+    }
+
+    fn request(&mut self, packet: &str) -> String {
+        self.send(packet);
+        self.reply()
+    }
+}
+
+#[test]
+fn registers_and_memory() {
+    let mut session = Session::new();
+    assert!(session
+        .request("qSupported:multiprocess+")
+        .contains("qXfer:features:read+"));
+    assert!(session
+        .request("qXfer:features:read:target.xml:0,fff")
+        .starts_with("l<?xml"));
+
+    // V0-VF, then I, PC and SP in little endian, then DT and ST
+    session.chip8.cpu.gp[0xA] = 0x42;
+    let registers = session.request("g");
+    assert_eq!(registers.len(), 2 * (16 + 2 + 2 + 2 + 1 + 1));
+    assert_eq!(&registers[20..22], "42");
+    assert_eq!(&registers[36..40], "0002");
+    assert_eq!(session.request("p11"), "0002");
+
+    assert_eq!(session.request("P10=0003"), "OK");
+    assert_eq!(session.chip8.cpu.i, 0x300);
+    assert_eq!(session.request("P12=ff00"), "E01");
+
+    assert_eq!(session.request("m200,6"), "600570011202");
+    assert_eq!(session.request("M300,2:abcd"), "OK");
+    assert_eq!(session.chip8.bus.ram.memory[0x301], 0xCD);
+    assert_eq!(session.request("mffff,2"), "E01");
+    // Ranges that do not fit in an address at all
+    assert_eq!(session.request("mffff,ffffffffffffffff"), "E01");
+    assert_eq!(session.request("Mffffffffffffffff,1:00"), "E01");
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    let mut session = Session::new();
+    assert_eq!(session.request("Z0,204,2"), "OK");
+    assert_eq!(session.request("c"), "S05");
+    assert_eq!(session.chip8.cpu.pc, 0x204);
+    assert_eq!(session.chip8.cpu.gp[0], 6);
+
+    assert_eq!(session.request("s"), "S05");
+    assert_eq!(session.chip8.cpu.pc, 0x202);
+
+    // Continuing runs past the breakpoint it stopped at, once around the loop
+    assert_eq!(session.request("c"), "S05");
+    assert_eq!(session.chip8.cpu.pc, 0x204);
+    assert_eq!(session.chip8.cpu.gp[0], 7);
+
+    assert_eq!(session.request("z0,204,2"), "OK");
+    session.send("c");
+    session.client.write_all(&[0x03]).unwrap();
+    assert_eq!(session.reply(), "S02");
+    assert!(session.debugger.paused);
+}
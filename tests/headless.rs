@@ -0,0 +1,166 @@
+use chip8::headless::{self, parse_args, pressed_keys, Press};
+use chip8::Chip8;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn presses_are_parsed() {
+    assert_eq!(
+        Press::parse("30:5"),
+        Ok(Press {
+            frame: 30,
+            key: 0x5,
+            frames: 1
+        })
+    );
+    assert_eq!(
+        Press::parse("0:a:10"),
+        Ok(Press {
+            frame: 0,
+            key: 0xA,
+            frames: 10
+        })
+    );
+    for invalid in ["30", "30:", "x:5", "30:G", "30:10", "30:5:x", "1:2:3:4"] {
+        assert!(Press::parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn presses_hold_their_keys_for_their_frames() {
+    let presses = [Press::parse("2:5:3").unwrap(), Press::parse("4:A").unwrap()];
+    let keys: Vec<u16> = (0..7).map(|frame| pressed_keys(&presses, frame)).collect();
+    assert_eq!(keys, [0, 0, 0x20, 0x20, 0x420, 0, 0]);
+
+    // A press that lasts past the last frame
+    let press = Press::parse(&format!("{}:1:10", u64::MAX - 1)).unwrap();
+    assert!(press.is_down(u64::MAX - 1));
+}
+
+#[test]
+fn options_are_parsed() {
+    let options = parse_args(&args("headless game.ch8")).unwrap();
+    assert_eq!(options.rom, "game.ch8");
+    assert_eq!(options.frames, headless::DEFAULT_FRAMES);
+    assert_eq!(options.instructions, None);
+    assert!(options.ascii);
+
+    let options = parse_args(&args(
+        "headless --quirks vip --frames 5 --instructions 9 --press 1:2 --pbm out.pbm game.ch8",
+    ))
+    .unwrap();
+    assert_eq!(options.quirks, chip8::quirks::Quirks::cosmac_vip());
+    assert_eq!(options.frames, 5);
+    assert_eq!(options.instructions, Some(9));
+    assert_eq!(options.presses.len(), 1);
+    assert_eq!(options.pbm.as_deref(), Some("out.pbm"));
+    assert!(!options.ascii);
+
+    assert!(parse_args(&args("headless")).is_err());
+    assert!(parse_args(&args("headless --frames")).is_err());
+    assert!(parse_args(&args("headless --wobble game.ch8")).is_err());
+    assert!(parse_args(&args("headless one.ch8 two.ch8")).is_err());
+    assert!(parse_args(&args("headless --press 1 game.ch8")).is_err());
+}
+
+#[test]
+fn runs_stop_at_the_frame_or_instruction_limit() {
+    // ADD V0, #01; JP #200
+    let rom = [0x70, 0x01, 0x12, 0x00];
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&rom).unwrap();
+    let options = parse_args(&args("headless --frames 3 game.ch8")).unwrap();
+    let (frames, result) = headless::run(&mut chip8, &options, None);
+    assert_eq!(frames, 3);
+    assert!(result.is_ok());
+    assert_eq!(chip8.instructions, 33);
+
+    // The limit cuts the second frame short
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&rom).unwrap();
+    let options = parse_args(&args("headless --instructions 15 game.ch8")).unwrap();
+    let (frames, _) = headless::run(&mut chip8, &options, None);
+    assert_eq!(frames, 2);
+    assert_eq!(chip8.instructions, 15);
+    assert_eq!(chip8.cpu.gp[0], 8);
+
+    let report = headless::report(&chip8, frames, true);
+    assert!(report.starts_with("frames: 2 instructions: 15\npc: 0202 "));
+    assert!(report.ends_with(&format!("{}\n", ".".repeat(64))));
+    assert_eq!(report.lines().filter(|line| line.len() == 64).count(), 32);
+    assert!(!headless::report(&chip8, frames, false).contains("...."));
+}
+
+// Runs the chip8-headless binary on a ROM
+fn headless(name: &str, rom: &[u8], options: &[&str]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join(name);
+    fs::write(&path, rom).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_chip8-headless"))
+        .args(options)
+        .arg(&path)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn exit_codes() {
+    // A ROM that runs until the frames run out exits cleanly
+    let output = headless("loop.ch8", &[0x12, 0x00], &["--frames", "4"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("frames: 4 instructions: 44\n"));
+
+    // So does one that exits by itself
+    let output = headless("exit.ch8", &[0x00, 0xFD], &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("frames: 1 instructions: 0\n"));
+
+    // An error in the ROM
+    let output = headless("bad.ch8", &[0x00, 0xEE], &[]);
+    assert_eq!(output.status.code(), Some(headless::EXIT_EMULATOR_ERROR));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Emulator halted"));
+
+    // A bad command line
+    let output = headless("loop.ch8", &[0x12, 0x00], &["--frames", "many"]);
+    assert_eq!(output.status.code(), Some(headless::EXIT_USAGE));
+    let output = Command::new(env!("CARGO_BIN_EXE_chip8-headless"))
+        .arg("no-such-rom.ch8")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(headless::EXIT_USAGE));
+}
+
+#[test]
+fn screen_output() {
+    // LD I, #000; DRW V0, V0, 5 draws the 0 of the font in the corner
+    let rom = [0xA0, 0x00, 0xD0, 0x05, 0x12, 0x04];
+    let output = headless("zero.ch8", &rom, &["--frames", "1"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let screen: Vec<&str> = stdout.lines().skip_while(|line| line.len() != 64).collect();
+    assert_eq!(screen.len(), 32);
+    assert!(screen[0].starts_with("####...."));
+    assert!(screen[1].starts_with("#..#...."));
+
+    let output = headless("zero.ch8", &rom, &["--frames", "1", "--pbm", "zero.pbm"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("####"));
+    let pbm =
+        fs::read_to_string(PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("zero.pbm")).unwrap();
+    let lines: Vec<&str> = pbm.lines().collect();
+    assert_eq!(lines[0], "P1");
+    assert_eq!(lines[1], "64 32");
+    assert!(lines[2].starts_with("1 1 1 1 0"));
+    assert!(lines[3].starts_with("1 0 0 1 0"));
+}
@@ -0,0 +1,31 @@
+use chip8::instruction::Instruction;
+
+#[test]
+fn every_instruction_encodes_to_its_own_word() {
+    let mut count = 0;
+    for opcode in 0..=0xFFFF {
+        if let Some(instruction) = Instruction::decode(opcode) {
+            assert_eq!(instruction.encode(), opcode, "{}", instruction);
+            count += 1;
+        }
+    }
+    // Everything but 0NNN and the gaps in the 5, 8, 9, E and F groups
+    assert_eq!(count, 44_585);
+}
+
+#[test]
+fn display_uses_chipper_mnemonics() {
+    let text = |opcode| Instruction::decode(opcode).unwrap().to_string();
+    assert_eq!(text(0x00E0), "CLS");
+    assert_eq!(text(0x1234), "JP    #234");
+    assert_eq!(text(0xD125), "DRW   V1, V2, 5");
+    assert_eq!(text(0x8106), "SHR   V1");
+    assert_eq!(text(0x8126), "SHR   V1, V2");
+    assert_eq!(text(0xF355), "LD    [I], V3");
+    assert_eq!(
+        Instruction::decode(0xD125),
+        Some(Instruction::Drw { x: 1, y: 2, n: 5 })
+    );
+    assert_eq!(Instruction::decode(0x0123), None);
+    assert_eq!(Instruction::LdLong.size(), 4);
+}
@@ -0,0 +1,43 @@
+use chip8::keymap::Keymap;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn games() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("games")
+}
+
+#[test]
+fn every_shipped_keymap_parses() {
+    let mut count = 0;
+    for entry in fs::read_dir(games()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "c8k") {
+            let text = fs::read_to_string(&path).unwrap();
+            let keymap: Keymap = text
+                .parse()
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(keymap.to_string(), text.trim());
+            count += 1;
+        }
+    }
+    assert_eq!(count, 14);
+}
+
+#[test]
+fn blinky_steers_with_2_4_6_8() {
+    let text = fs::read_to_string(games().join("BLINKY.c8k")).unwrap();
+    let keymap: Keymap = text.parse().unwrap();
+
+    // Up is key 3, down 6, left 7 and right 8
+    assert_eq!(keymap.apply(1 << 0x8), 1 << 0x6);
+    assert_eq!(keymap.apply(1 << 0x4), 1 << 0x4 | 1 << 0x7);
+    assert_eq!(keymap.apply(1 << 0x6), 1 << 0x8);
+    // 2 presses both 2 and 3, nothing is left under 3
+    assert_eq!(keymap.apply(1 << 0x2), 1 << 0x2 | 1 << 0x3);
+    assert_eq!(keymap.apply(1 << 0x3), 0);
+
+    assert_eq!(Keymap::default().apply(0xA5A5), 0xA5A5);
+    assert!("0123".parse::<Keymap>().is_err());
+    assert!("0123456789ABCDEG".parse::<Keymap>().is_err());
+}
@@ -0,0 +1,125 @@
+use chip8::cpu::{KeyWait, StepOutcome};
+use chip8::keypad::Keypad;
+use chip8::quirks::Quirks;
+use chip8::Chip8;
+
+#[test]
+fn keys_are_released_one_by_one() {
+    let mut keypad = Keypad::new();
+    keypad.press_key(0x5);
+    keypad.press_key(0x8);
+    keypad.next_frame();
+
+    keypad.release_key(0x5);
+    assert!(!keypad.is_key_down(0x5));
+    assert!(keypad.is_key_down(0x8));
+    assert_eq!(keypad.released(), 1 << 0x5);
+
+    // A repeated press of a held key is not a new press
+    keypad.press_key(0x8);
+    assert_eq!(keypad.pressed(), 0);
+}
+
+#[test]
+fn taps_last_until_the_frame_ends() {
+    let mut keypad = Keypad::new();
+    keypad.press_key(0xA);
+    keypad.release_key(0xA);
+    assert!(keypad.is_key_down(0xA));
+    assert_eq!(keypad.pressed(), 1 << 0xA);
+
+    keypad.next_frame();
+    assert!(!keypad.is_key_down(0xA));
+    assert_eq!(keypad.pressed(), 0);
+    assert_eq!(keypad.released(), 1 << 0xA);
+
+    keypad.next_frame();
+    assert_eq!(keypad.released(), 0);
+}
+
+#[test]
+fn wait_for_key_needs_a_new_press() {
+    // LD V3, K; JP #202
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&[0xF3, 0x0A, 0x12, 0x02]).unwrap();
+
+    // Held from before the wait started
+    chip8.bus.keypad.press_key(0x7);
+    chip8.end_frame();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+    assert_eq!(chip8.cpu.pc, 0x200);
+
+    chip8.bus.keypad.press_key(0xC);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    assert_eq!(chip8.cpu.gp[3], 0xC);
+    assert_eq!(chip8.cpu.pc, 0x202);
+}
+
+#[test]
+fn wait_for_key_release_on_the_vip() {
+    let mut chip8 = Chip8::new(Quirks::cosmac_vip());
+    chip8.load_rom(&[0xF3, 0x0A, 0x12, 0x02]).unwrap();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+
+    chip8.bus.keypad.press_key(0x4);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+    assert_eq!(
+        chip8.cpu.key_wait,
+        Some(KeyWait::Release { x: 3, key: 0x4 })
+    );
+
+    // The wait survives a save state
+    let state = chip8.save_state();
+    let mut restored = Chip8::new(Quirks::cosmac_vip());
+    restored.load_rom(&[0xF3, 0x0A, 0x12, 0x02]).unwrap();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.cpu.key_wait, chip8.cpu.key_wait);
+
+    chip8.end_frame();
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+    chip8.bus.keypad.release_key(0x4);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    assert_eq!(chip8.cpu.gp[3], 0x4);
+    assert_eq!(chip8.cpu.pc, 0x202);
+    assert_eq!(chip8.cpu.key_wait, None);
+}
+
+#[test]
+fn skips_check_the_key_in_the_register() {
+    // LD V1, #0B; SKP V1; LD V2, #01; SKNP V1; LD V3, #01
+    let rom = [0x61, 0x0B, 0xE1, 0x9E, 0x62, 0x01, 0xE1, 0xA1, 0x63, 0x01];
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&rom).unwrap();
+    chip8.bus.keypad.press_key(0xB);
+    for _ in 0..4 {
+        chip8.step().unwrap();
+    }
+    assert_eq!(chip8.cpu.gp[2], 0);
+    assert_eq!(chip8.cpu.gp[3], 1);
+}
+
+#[test]
+fn restoring_a_snapshot_forgets_the_edges() {
+    // LD V3, K; JP #202
+    let rom = [0xF3, 0x0A, 0x12, 0x02];
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&rom).unwrap();
+    chip8.bus.keypad.press_key(0x7);
+    let snapshot = chip8.snapshot();
+
+    // A key that was pressed and let go of after the snapshot was taken
+    chip8.bus.keypad.press_key(0x2);
+    chip8.bus.keypad.release_key(0x7);
+    chip8.restore_snapshot(&snapshot).unwrap();
+
+    let keypad = &chip8.bus.keypad;
+    assert_eq!(keypad.state(), 1 << 0x7);
+    assert_eq!(keypad.pressed(), 0);
+    assert_eq!(keypad.released(), 0);
+    assert_eq!(chip8.step().unwrap(), StepOutcome::WaitingForKey);
+
+    // The tap of key 2 does not come back at the end of the frame
+    chip8.end_frame();
+    assert_eq!(chip8.bus.keypad.state(), 1 << 0x7);
+    assert_eq!(chip8.bus.keypad.released(), 0);
+}
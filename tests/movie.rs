@@ -0,0 +1,150 @@
+use chip8::movie::{Movie, MoviePlayer};
+use chip8::quirks::Quirks;
+use chip8::Chip8;
+
+// RND V1, #FF; LD V0, #05; SKP V0; JP #200; ADD V2, #01; JP #200
+const ROM: [u8; 12] = [
+    0xC1, 0xFF, 0x60, 0x05, 0xE0, 0x9E, 0x12, 0x00, 0x72, 0x01, 0x12, 0x00,
+];
+
+fn machine(quirks: Quirks) -> Chip8 {
+    let mut chip8 = Chip8::new(quirks);
+    chip8.load_rom(&ROM).unwrap();
+    chip8
+}
+
+#[test]
+fn text_round_trip() {
+    let mut movie = Movie::new(&machine(Quirks::cosmac_vip()), 1234);
+    for frame in 0..50 {
+        movie.record(if (10..20).contains(&frame) { 0x0020 } else { 0 }, 0);
+    }
+    let text = movie.to_string();
+    assert!(text.contains("\n10 0020\n20 0000\n"));
+
+    let parsed = Movie::parse(&text).unwrap();
+    assert_eq!(parsed.to_string(), text);
+    assert_eq!(parsed.quirks, Quirks::cosmac_vip());
+    assert_eq!(parsed.seed, 1234);
+    assert_eq!(parsed.frames, 50);
+}
+
+#[test]
+fn header_and_quirk_errors() {
+    let text = Movie::new(&machine(Quirks::default()), 1).to_string();
+    let replace = |from: &str, to: &str| Movie::parse(&text.replacen(from, to, 1));
+
+    assert!(replace("chip8-movie", "chip9-movie").is_err());
+    assert!(replace("chip8-movie 2", "chip8-movie 7").is_err());
+    assert!(replace("chip8-movie 2", "chip8-movie 0").is_err());
+    assert!(replace("seed 1", "seed one").is_err());
+    assert!(replace("ipf ", "speed ").is_err());
+    assert_eq!(
+        replace("shift=true", "shift=maybe").err().unwrap(),
+        "invalid quirk 'shift=maybe'"
+    );
+    assert_eq!(
+        replace("shift=true", "wobble=true").err().unwrap(),
+        "unknown quirk 'wobble'"
+    );
+    assert!(Movie::parse(&format!("{}12 zz\n", text)).is_err());
+    assert!(Movie::parse("chip8-movie 2\nrom 00").is_err());
+}
+
+#[test]
+fn version_1_movies_still_play() {
+    let text = "chip8-movie 1\n\
+                rom 00000000000000ff\n\
+                seed 5\n\
+                ipf 11\n\
+                quirks shift=false load_store=true jump=false vf_reset=true \
+                clipping=true display_wait=true\n\
+                frames 2\n\
+                1 0001\n";
+    let movie = Movie::parse(text).unwrap();
+    assert_eq!(
+        movie.quirks,
+        Quirks {
+            key_release: Quirks::default().key_release,
+            ..Quirks::cosmac_vip()
+        }
+    );
+    assert_eq!(movie.changes, [(1, 0x0001, 0)]);
+    assert!(movie.to_string().starts_with("chip8-movie 2\n"));
+}
+
+#[test]
+fn playback_reproduces_the_run() {
+    // Record a run that depends on both the keypad and the random numbers
+    let mut chip8 = machine(Quirks::default());
+    chip8.cpu.seed_rng(99);
+    let mut movie = Movie::new(&chip8, 99);
+    for frame in 0..120 {
+        let keys = if frame % 30 < 8 { 1 << 5 } else { 0 };
+        chip8.bus.keypad.set_state(keys);
+        movie.record(chip8.bus.keypad.state(), chip8.bus.keypad.pressed());
+        chip8.run_frame().unwrap();
+    }
+    assert!(chip8.cpu.gp[2] > 0);
+
+    let mut replay = machine(Quirks::default());
+    let movie = Movie::parse(&movie.to_string()).unwrap();
+    movie.apply_settings(&mut replay).unwrap();
+    let mut player = MoviePlayer::new(movie);
+    while let Some((keys, repressed)) = player.next_frame() {
+        replay.bus.keypad.set_state(keys);
+        replay.bus.keypad.repress(repressed);
+        replay.run_frame().unwrap();
+    }
+    assert_eq!(replay.snapshot(), chip8.snapshot());
+
+    // A movie only plays on the ROM it was recorded with
+    let mut other = Chip8::default();
+    other.load_rom(&[0x12, 0x00]).unwrap();
+    let movie = Movie::new(&chip8, 99);
+    assert!(movie.apply_settings(&mut other).is_err());
+}
+
+#[test]
+fn keys_pressed_again_between_frames_play_back() {
+    // LD V1, K; ADD V2, #01; JP #200 counts the keys FX0A sees go down
+    let rom = [0xF1, 0x0A, 0x72, 0x01, 0x12, 0x00];
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&rom).unwrap();
+    let mut movie = Movie::new(&chip8, 0);
+    for frame in 0..4 {
+        let keypad = &mut chip8.bus.keypad;
+        match frame {
+            0 => keypad.press_key(0x5),
+            // Let go of 5 and press it again before the frame starts, so it
+            // is down at the start of every frame
+            2 => {
+                keypad.release_key(0x5);
+                keypad.press_key(0x5);
+            }
+            _ => {}
+        }
+        movie.record(keypad.state(), keypad.pressed());
+        chip8.run_frame().unwrap();
+    }
+    assert_eq!(chip8.cpu.gp[2], 2);
+
+    let text = movie.to_string();
+    assert!(text.ends_with("\n0 0020\n2 0020 0020\n"));
+    let movie = Movie::parse(&text).unwrap();
+    assert_eq!(movie.changes, [(0, 0x0020, 0), (2, 0x0020, 0x0020)]);
+    assert!(Movie::parse(&text.replace("0020 0020", "0020 zz")).is_err());
+    assert!(Movie::parse(&text.replace("0020 0020", "0020 0020 0")).is_err());
+
+    let mut replay = Chip8::default();
+    replay.load_rom(&rom).unwrap();
+    movie.apply_settings(&mut replay).unwrap();
+    let mut player = MoviePlayer::new(movie);
+    while let Some((keys, repressed)) = player.next_frame() {
+        replay.bus.keypad.set_state(keys);
+        replay.bus.keypad.repress(repressed);
+        replay.run_frame().unwrap();
+    }
+    assert_eq!(replay.cpu.gp[2], 2);
+    assert_eq!(replay.snapshot(), chip8.snapshot());
+}
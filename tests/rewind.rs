@@ -0,0 +1,47 @@
+use chip8::rewind::{apply_delta, encode_delta, Rewind};
+use chip8::Chip8;
+
+// LD V0, #00; ADD V0, #01; LD I, #300; LD B, V0; JP #202
+const COUNTER: [u8; 10] = [0x60, 0x00, 0x70, 0x01, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x02];
+
+#[test]
+fn deltas_turn_one_snapshot_into_the_other_and_back() {
+    let from: Vec<u8> = (0..300).map(|idx| (idx % 251) as u8).collect();
+    let mut to = from.clone();
+    to[0] ^= 0xFF;
+    to[5..9].copy_from_slice(&[1, 2, 3, 4]);
+    // A run of unchanged bytes long enough for a two byte count
+    to[299] = 0;
+
+    let delta = encode_delta(&from, &to);
+    assert!(delta.len() < 20);
+    let mut snapshot = to.clone();
+    apply_delta(&delta, &mut snapshot);
+    assert_eq!(snapshot, from);
+    apply_delta(&delta, &mut snapshot);
+    assert_eq!(snapshot, to);
+
+    assert!(encode_delta(&from, &from).is_empty());
+}
+
+#[test]
+fn rewinding_restores_the_captured_snapshots() {
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&COUNTER).unwrap();
+    let mut rewind = Rewind::new(10, 1);
+
+    let mut snapshots = Vec::new();
+    for _ in 0..20 {
+        chip8.run_frame().unwrap();
+        rewind.capture(&chip8);
+        snapshots.push(chip8.snapshot());
+    }
+
+    // Each step goes back to the snapshot before the current one
+    for expected in snapshots.iter().rev().skip(1) {
+        assert!(rewind.rewind(&mut chip8).unwrap());
+        assert_eq!(&chip8.snapshot(), expected);
+    }
+    assert!(!rewind.rewind(&mut chip8).unwrap());
+    assert_eq!(chip8.snapshot(), snapshots[0]);
+}
@@ -0,0 +1,109 @@
+use chip8::display::PLANES;
+use chip8::savestate::{StateError, SNAPSHOT_SIZE};
+use chip8::Chip8;
+
+// LD V0, #05; CALL #206; JP #202; RET
+const ROM: [u8; 8] = [0x60, 0x05, 0x22, 0x06, 0x12, 0x02, 0x00, 0xEE];
+
+fn machine() -> Chip8 {
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&ROM).unwrap();
+    chip8
+}
+
+#[test]
+fn impossible_values_are_rejected() {
+    let mut chip8 = machine();
+    // The stack pointer follows the registers, pc, I and the stack
+    let sp = 16 + 2 + 2 + 16 * 2;
+    let mut snapshot = chip8.snapshot();
+    snapshot[sp] = 40;
+    assert_eq!(
+        chip8.restore_snapshot(&snapshot),
+        Err(StateError::Invalid("stack pointer"))
+    );
+    assert_eq!(chip8.cpu.sp, 0);
+    assert_eq!(chip8.cpu.pc, 0x200);
+
+    // A key wait for V17, it comes after sp, the timers, the audio
+    // pattern, pitch, the user flags, the operand and vblank
+    let mut snapshot = chip8.snapshot();
+    assert_eq!(snapshot.len(), SNAPSHOT_SIZE);
+    let key_wait = sp + 2 + 1 + 1 + 1 + 16 + 1 + 16 + 2 + 1;
+    snapshot[key_wait] = 1;
+    snapshot[key_wait + 1] = 0x11;
+    assert_eq!(
+        chip8.restore_snapshot(&snapshot),
+        Err(StateError::Invalid("key wait"))
+    );
+}
+
+// A machine some frames into the ROM, with things on screen and the
+// timers running
+fn running_machine() -> Chip8 {
+    let mut chip8 = machine();
+    chip8.cpu.dt = 30;
+    chip8.cpu.st = 12;
+    chip8
+        .bus
+        .display
+        .draw(PLANES[0], 3, 4, &[0xF0, 0x90], false);
+    for _ in 0..3 {
+        chip8.run_frame().unwrap();
+    }
+    chip8
+}
+
+#[test]
+fn round_trip() {
+    let chip8 = running_machine();
+    let state = chip8.save_state();
+
+    let mut restored = machine();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.cpu.gp, chip8.cpu.gp);
+    assert_eq!(restored.cpu.pc, chip8.cpu.pc);
+    assert_eq!(restored.cpu.i, chip8.cpu.i);
+    assert_eq!(restored.cpu.sp, chip8.cpu.sp);
+    assert_eq!(restored.cpu.stack, chip8.cpu.stack);
+    assert_eq!((restored.cpu.dt, restored.cpu.st), (27, 9));
+    assert!(chip8.bus.display.gfx.iter().any(|pixel| *pixel != 0));
+    assert_eq!(restored.bus.display.gfx, chip8.bus.display.gfx);
+    assert_eq!(restored.snapshot(), chip8.snapshot());
+}
+
+#[test]
+fn states_of_other_roms_are_rejected() {
+    let state = running_machine().save_state();
+    let mut other = Chip8::default();
+    other.load_rom(&[0x12, 0x00]).unwrap();
+    assert!(matches!(
+        other.load_state(&state),
+        Err(StateError::WrongRom { .. })
+    ));
+    assert_eq!(other.cpu.pc, 0x200);
+}
+
+#[test]
+fn bad_headers_and_truncated_states_are_rejected() {
+    let state = running_machine().save_state();
+    let mut chip8 = machine();
+
+    let mut bad_magic = state.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(chip8.load_state(&bad_magic), Err(StateError::NotASaveState));
+    assert_eq!(chip8.load_state(b"C8"), Err(StateError::NotASaveState));
+
+    let mut bad_version = state.clone();
+    bad_version[4..6].copy_from_slice(&99u16.to_le_bytes());
+    assert_eq!(
+        chip8.load_state(&bad_version),
+        Err(StateError::UnsupportedVersion(99))
+    );
+
+    assert_eq!(
+        chip8.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+    assert_eq!(chip8.cpu.pc, 0x200);
+}
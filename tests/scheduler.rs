@@ -0,0 +1,93 @@
+use chip8::cpu::StepOutcome;
+use chip8::quirks::Quirks;
+use chip8::Chip8;
+
+fn machine(quirks: Quirks, rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new(quirks);
+    chip8.load_rom(rom).unwrap();
+    chip8
+}
+
+// ADD V0, #01 over and over
+fn counter() -> Vec<u8> {
+    [0x70, 0x01].repeat(100)
+}
+
+#[test]
+fn a_frame_runs_instructions_per_frame_steps() {
+    let mut chip8 = machine(Quirks::default(), &counter());
+    chip8.instructions_per_frame = 7;
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::Executed);
+    assert_eq!(chip8.cpu.gp[0], 7);
+    assert_eq!(chip8.cpu.pc, 0x200 + 7 * 2);
+
+    chip8.instructions_per_frame = 3;
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.cpu.gp[0], 10);
+}
+
+#[test]
+fn timers_count_down_once_per_frame() {
+    // LD V1, #10; LD DT, V1; LD ST, V1; ADD V0, #01; JP #206
+    let rom = [0x61, 0x10, 0xF1, 0x15, 0xF1, 0x18, 0x70, 0x01, 0x12, 0x06];
+    let mut chip8 = machine(Quirks::default(), &rom);
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.cpu.dt, 0x0F);
+    assert_eq!(chip8.cpu.st, 0x0F);
+    assert!(chip8.is_buzzer_on());
+
+    for _ in 0..0x0F {
+        chip8.run_frame().unwrap();
+    }
+    assert_eq!(chip8.cpu.dt, 0);
+    assert_eq!(chip8.cpu.st, 0);
+    assert!(!chip8.is_buzzer_on());
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.cpu.dt, 0);
+}
+
+#[test]
+fn frames_end_early_when_waiting_for_the_display() {
+    // DRW V0, V0, 1; ADD V1, #01; JP #200 draws once per frame
+    let rom = [0xD0, 0x01, 0x71, 0x01, 0x12, 0x00];
+    let mut chip8 = machine(Quirks::cosmac_vip(), &rom);
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::WaitingForDisplay);
+    assert_eq!(chip8.cpu.gp[1], 1);
+    assert_eq!(chip8.cpu.pc, 0x200);
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::WaitingForDisplay);
+    assert_eq!(chip8.cpu.gp[1], 2);
+
+    // Without the quirk the whole frame runs
+    let mut chip8 = machine(Quirks::default(), &rom);
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::Executed);
+    assert_eq!(chip8.cpu.gp[1], 4);
+}
+
+#[test]
+fn frames_end_early_on_exit() {
+    // ADD V0, #01; EXIT
+    let mut chip8 = machine(Quirks::default(), &[0x70, 0x01, 0x00, 0xFD]);
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::Exited);
+    assert_eq!(chip8.cpu.gp[0], 1);
+    assert_eq!(chip8.cpu.pc, 0x202);
+    assert_eq!(chip8.run_frame().unwrap(), StepOutcome::Exited);
+    assert_eq!(chip8.cpu.gp[0], 1);
+}
+
+#[test]
+fn the_keypad_starts_a_new_frame_at_the_end_of_each() {
+    let mut chip8 = machine(Quirks::default(), &counter());
+    chip8.bus.keypad.press_key(0x3);
+    chip8.bus.keypad.release_key(0x3);
+    assert_eq!(chip8.bus.keypad.pressed(), 1 << 0x3);
+    assert!(chip8.bus.keypad.is_key_down(0x3));
+
+    // The tap lasts the whole frame, then the key goes up
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.bus.keypad.pressed(), 0);
+    assert_eq!(chip8.bus.keypad.released(), 1 << 0x3);
+    assert!(!chip8.bus.keypad.is_key_down(0x3));
+
+    chip8.run_frame().unwrap();
+    assert_eq!(chip8.bus.keypad.released(), 0);
+}
@@ -0,0 +1,73 @@
+use chip8::trace::Trace;
+use chip8::Chip8;
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// LD V0, #05; ADD V0, #01; CALL #208; JP #204; RET
+const ROM: [u8; 10] = [0x60, 0x05, 0x70, 0x01, 0x22, 0x08, 0x12, 0x04, 0x00, 0xEE];
+
+// Collects the trace so the test can read it back
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn trace(steps: usize, setup: impl Fn(&mut Trace)) -> Vec<String> {
+    let buffer = Buffer::default();
+    let mut trace = Trace::new(Box::new(buffer.clone()));
+    setup(&mut trace);
+
+    let mut chip8 = Chip8::default();
+    chip8.load_rom(&ROM).unwrap();
+    chip8.trace = Some(trace);
+    for _ in 0..steps {
+        chip8.step().unwrap();
+    }
+    chip8.trace.as_mut().unwrap().finish().unwrap();
+
+    let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+    text.lines().map(String::from).collect()
+}
+
+#[test]
+fn lines_show_the_changed_registers() {
+    assert_eq!(
+        trace(6, |_| {}),
+        [
+            "0200  6005  LD    V0, #05         V0=05",
+            "0202  7001  ADD   V0, #01         V0=06",
+            "0204  2208  CALL  #208            SP=1",
+            "0208  00EE  RET                   SP=0",
+            "0206  1204  JP    #204",
+            "0204  2208  CALL  #208            SP=1",
+        ]
+    );
+}
+
+#[test]
+fn filters_on_address_and_count() {
+    let lines = trace(20, |trace| {
+        trace.set_range(Trace::parse_range("206-208").unwrap());
+        trace.set_limit(3);
+    });
+    assert_eq!(
+        lines,
+        [
+            "0208  00EE  RET                   SP=0",
+            "0206  1204  JP    #204",
+            "0208  00EE  RET                   SP=0",
+        ]
+    );
+    assert!(Trace::parse_range("300-200").is_err());
+}